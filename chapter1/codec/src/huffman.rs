@@ -0,0 +1,242 @@
+// huffman.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Canonical Huffman coding: frequent bytes get short codes, rare bytes long ones.
+// The input is coded in blocks of up to BLOCK_SIZE bytes, each with its own code.
+// As the code is canonical, only the code length of each byte has to be stored:
+// block length (u32, little-endian; 0 marks the end), 256 code lengths (one byte each),
+// then the codes of the block's bytes, padded to a whole byte.
+use super::{invalid_data, read_block, BitReader, BitWriter, Codec};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+pub const BLOCK_SIZE: usize = 1 << 16;
+// With at most 2^16 bytes per block no code can be longer than 23 bits (Fibonacci bound)
+const MAX_CODE_LENGTH: usize = 32;
+
+pub struct Huffman;
+
+// Code length of every byte value, 0 for bytes not occurring
+pub fn code_lengths(frequencies: &[u64; 256]) -> [u8; 256] {
+    let mut lengths = [0; 256];
+    // nodes 0..256 are the leaves, further nodes the inner nodes of the Huffman tree
+    let mut parents: Vec<usize> = vec![usize::MAX; 256];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, frequency)| **frequency > 0)
+        .map(|(symbol, frequency)| Reverse((*frequency, symbol)))
+        .collect();
+    if heap.len() == 1 {
+        let Reverse((_, symbol)) = heap.pop().unwrap();
+        lengths[symbol] = 1;
+        return lengths;
+    }
+    while heap.len() > 1 {
+        let Reverse((frequency1, node1)) = heap.pop().unwrap();
+        let Reverse((frequency2, node2)) = heap.pop().unwrap();
+        let parent = parents.len();
+        parents.push(usize::MAX);
+        parents[node1] = parent;
+        parents[node2] = parent;
+        heap.push(Reverse((frequency1 + frequency2, parent)));
+    }
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        if frequencies[symbol] > 0 {
+            let mut node = symbol;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                *length += 1;
+            }
+        }
+    }
+    lengths
+}
+
+// Number of codes of each length; fails for lengths that do not form a prefix code
+fn length_counts(lengths: &[u8; 256]) -> io::Result<[u64; MAX_CODE_LENGTH + 1]> {
+    let mut counts = [0; MAX_CODE_LENGTH + 1];
+    for &length in lengths {
+        if length as usize > MAX_CODE_LENGTH {
+            return Err(invalid_data("Huffman code too long"));
+        }
+        counts[length as usize] += 1;
+    }
+    counts[0] = 0;
+    // Kraft's inequality: the sum of 2^-length may not exceed 1
+    let kraft: u64 = (1..=MAX_CODE_LENGTH)
+        .map(|length| counts[length] << (MAX_CODE_LENGTH - length))
+        .sum();
+    if kraft > 1 << MAX_CODE_LENGTH {
+        return Err(invalid_data("Huffman code lengths are oversubscribed"));
+    }
+    Ok(counts)
+}
+
+// Canonical codes: codes of the same length are consecutive numbers in byte order,
+// and shorter codes come before longer ones
+pub fn canonical_codes(lengths: &[u8; 256]) -> io::Result<[u32; 256]> {
+    let counts = length_counts(lengths)?;
+    let mut next_code = [0u64; MAX_CODE_LENGTH + 1];
+    let mut code = 0;
+    for length in 1..=MAX_CODE_LENGTH {
+        code = (code + counts[length - 1]) << 1;
+        next_code[length] = code;
+    }
+    let mut codes = [0; 256];
+    for (symbol, &length) in lengths.iter().enumerate() {
+        if length > 0 {
+            codes[symbol] = next_code[length as usize] as u32;
+            next_code[length as usize] += 1;
+        }
+    }
+    Ok(codes)
+}
+
+struct Decoder {
+    counts: [u64; MAX_CODE_LENGTH + 1],
+    // bytes ordered by code length, then by value: the order of their canonical codes
+    symbols: Vec<u8>,
+}
+
+impl Decoder {
+    fn new(lengths: &[u8; 256]) -> io::Result<Self> {
+        let counts = length_counts(lengths)?;
+        let mut symbols: Vec<u8> = (0..=255)
+            .filter(|&symbol| lengths[symbol as usize] > 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Ok(Decoder { counts, symbols })
+    }
+
+    // Codes of length l are the numbers first..first + counts[l], where first
+    // follows from the codes of all shorter lengths
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u8> {
+        let (mut code, mut first, mut index) = (0u64, 0u64, 0u64);
+        for length in 1..=MAX_CODE_LENGTH {
+            code |= u64::from(reader.read_bit()?);
+            let count = self.counts[length];
+            if code < first + count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("invalid Huffman code"))
+    }
+}
+
+impl Codec for Huffman {
+    fn name(&self) -> &'static str {
+        "huffman"
+    }
+
+    fn encode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut output = BufWriter::new(output);
+        let mut writer = BitWriter::new(&mut output);
+        let mut block = vec![0; BLOCK_SIZE];
+        loop {
+            let length = read_block(input, &mut block)?;
+            writer.write_bytes(&(length as u32).to_le_bytes())?;
+            if length == 0 {
+                break;
+            }
+            let mut frequencies = [0; 256];
+            for &byte in &block[..length] {
+                frequencies[byte as usize] += 1;
+            }
+            let lengths = code_lengths(&frequencies);
+            let codes = canonical_codes(&lengths)?;
+            writer.write_bytes(&lengths)?;
+            for &byte in &block[..length] {
+                writer.write_bits(codes[byte as usize], lengths[byte as usize])?;
+            }
+        }
+        writer.finish()
+    }
+
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut input = BufReader::new(input);
+        let mut reader = BitReader::new(&mut input);
+        let mut output = BufWriter::new(output);
+        let mut block = Vec::with_capacity(BLOCK_SIZE);
+        loop {
+            let mut header = [0; 4];
+            reader.read_bytes(&mut header)?;
+            let length = u32::from_le_bytes(header) as usize;
+            if length == 0 {
+                break;
+            }
+            if length > BLOCK_SIZE {
+                return Err(invalid_data("Huffman block too large"));
+            }
+            let mut lengths = [0; 256];
+            reader.read_bytes(&mut lengths)?;
+            let decoder = Decoder::new(&lengths)?;
+            block.clear();
+            for _ in 0..length {
+                block.push(decoder.decode(&mut reader)?);
+            }
+            output.write_all(&block)?;
+        }
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_canonical_prefix_codes() {
+        let mut frequencies = [0; 256];
+        frequencies[b'a' as usize] = 45;
+        frequencies[b'b' as usize] = 13;
+        frequencies[b'c' as usize] = 12;
+        frequencies[b'd' as usize] = 16;
+        frequencies[b'e' as usize] = 9;
+        frequencies[b'f' as usize] = 5;
+        let lengths = code_lengths(&frequencies);
+        let expected = [
+            (b'a', 1),
+            (b'b', 3),
+            (b'c', 3),
+            (b'd', 3),
+            (b'e', 4),
+            (b'f', 4),
+        ];
+        for (symbol, length) in expected {
+            assert_eq!(lengths[symbol as usize], length);
+        }
+        let codes = canonical_codes(&lengths).unwrap();
+        let expected = [
+            (b'a', 0b0),
+            (b'b', 0b100),
+            (b'c', 0b101),
+            (b'd', 0b110),
+            (b'e', 0b1110),
+            (b'f', 0b1111),
+        ];
+        for (symbol, code) in expected {
+            assert_eq!(codes[symbol as usize], code);
+        }
+        let mut oversubscribed = [0; 256];
+        oversubscribed[..3].copy_from_slice(&[1, 1, 1]);
+        assert!(canonical_codes(&oversubscribed).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// codec/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Lossless compression of arbitrary bytes, going beyond the 2-bit encoding of genomes.
+// All codecs work on streams: they read their input and write their output piece by piece,
+// so files larger than memory can be compressed. Corrupted input to decode results
+// in an io::Error of kind InvalidData (or UnexpectedEof if it is truncated).
+use std::io::{self, Read, Write};
+
+pub mod huffman;
+pub mod lz77;
+pub mod rle;
+
+pub trait Codec {
+    fn name(&self) -> &'static str;
+    fn encode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()>;
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()>;
+
+    fn encode_bytes(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.encode(&mut &data[..], &mut output)?;
+        Ok(output)
+    }
+    fn decode_bytes(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        self.decode(&mut &data[..], &mut output)?;
+        Ok(output)
+    }
+}
+
+pub fn all_codecs() -> Vec<Box<dyn Codec>> {
+    vec![
+        Box::new(huffman::Huffman),
+        Box::new(lz77::Lzss),
+        Box::new(rle::RunLength),
+    ]
+}
+
+pub fn codec_by_name(name: &str) -> Option<Box<dyn Codec>> {
+    all_codecs().into_iter().find(|codec| codec.name() == name)
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Fill buffer as far as possible, returning the number of bytes read
+pub(crate) fn read_block(input: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+// Writes bits most significant first, padding the last byte with zeros
+pub(crate) struct BitWriter<'a> {
+    output: &'a mut dyn Write,
+    buffer: u8,
+    count: u8,
+}
+
+impl<'a> BitWriter<'a> {
+    pub(crate) fn new(output: &'a mut dyn Write) -> Self {
+        BitWriter {
+            output,
+            buffer: 0,
+            count: 0,
+        }
+    }
+    pub(crate) fn write_bits(&mut self, value: u32, bits: u8) -> io::Result<()> {
+        for shift in (0..bits).rev() {
+            self.buffer = (self.buffer << 1) | ((value >> shift) & 1) as u8;
+            self.count += 1;
+            if self.count == 8 {
+                self.output.write_all(&[self.buffer])?;
+                self.buffer = 0;
+                self.count = 0;
+            }
+        }
+        Ok(())
+    }
+    // Pad to the next byte boundary
+    pub(crate) fn align(&mut self) -> io::Result<()> {
+        if self.count > 0 {
+            self.write_bits(0, 8 - self.count)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.align()?;
+        self.output.write_all(bytes)
+    }
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.align()?;
+        self.output.flush()
+    }
+}
+
+pub(crate) struct BitReader<'a> {
+    input: &'a mut dyn Read,
+    buffer: u8,
+    count: u8,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(input: &'a mut dyn Read) -> Self {
+        BitReader {
+            input,
+            buffer: 0,
+            count: 0,
+        }
+    }
+    pub(crate) fn read_bit(&mut self) -> io::Result<u32> {
+        if self.count == 0 {
+            let mut byte = [0];
+            self.input.read_exact(&mut byte)?;
+            self.buffer = byte[0];
+            self.count = 8;
+        }
+        self.count -= 1;
+        Ok(u32::from((self.buffer >> self.count) & 1))
+    }
+    pub(crate) fn read_bits(&mut self, bits: u8) -> io::Result<u32> {
+        let mut value = 0;
+        for _ in 0..bits {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+    // Skip the padding up to the next byte boundary
+    pub(crate) fn align(&mut self) {
+        self.count = 0;
+    }
+    pub(crate) fn read_bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        self.align();
+        self.input.read_exact(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn test_inputs() -> Vec<Vec<u8>> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let random: Vec<u8> = (0..100_000).map(|_| rng.random()).collect();
+        // few distinct bytes in runs of random length
+        let mut runs = Vec::new();
+        while runs.len() < 300_000 {
+            let byte: u8 = rng.random_range(0..4);
+            let length = rng.random_range(1..300);
+            runs.extend(std::iter::repeat_n(byte, length));
+        }
+        let text = "TAGGGATTAACCGTTATATATATATAGCCATGGATCGATTATA the quick brown fox "
+            .repeat(2000)
+            .into_bytes();
+        vec![
+            Vec::new(),
+            vec![7],
+            vec![0; 1000],
+            (0..=255).collect(),
+            random,
+            runs,
+            text,
+        ]
+    }
+
+    #[test]
+    fn all_codecs_round_trip() {
+        for codec in all_codecs() {
+            for input in test_inputs() {
+                let encoded = codec.encode_bytes(&input).unwrap();
+                let decoded = codec.decode_bytes(&encoded).unwrap();
+                assert!(
+                    decoded == input,
+                    "{} does not round-trip {} bytes",
+                    codec.name(),
+                    input.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn codecs_compress_redundant_data() {
+        let text = "to be or not to be, that is the question ".repeat(100);
+        for codec in all_codecs() {
+            let encoded = codec.encode_bytes(&[b'x'; 10_000]).unwrap();
+            assert!(
+                encoded.len() < 2_000,
+                "{} does not compress runs",
+                codec.name()
+            );
+            if codec.name() != "rle" {
+                let encoded = codec.encode_bytes(text.as_bytes()).unwrap();
+                assert!(encoded.len() < text.len() * 3 / 4, "{}", codec.name());
+            }
+        }
+    }
+
+    #[test]
+    fn decoding_garbage_fails_cleanly() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for codec in all_codecs() {
+            let encoded = codec.encode_bytes(&[1, 2, 3, 4, 5, 1, 2, 3, 4, 5]).unwrap();
+            assert!(codec.decode_bytes(&encoded[..encoded.len() - 1]).is_err());
+            // random bytes must not panic, whatever the result
+            for _ in 0..100 {
+                let garbage: Vec<u8> = (0..rng.random_range(0..200))
+                    .map(|_| rng.random())
+                    .collect();
+                let _ = codec.decode_bytes(&garbage);
+            }
+        }
+        assert!(codec_by_name("huffman").is_some());
+        assert!(codec_by_name("zip").is_none());
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(&mut bytes);
+        writer.write_bits(0b101, 3).unwrap();
+        writer.write_bits(0xABCD, 16).unwrap();
+        writer.write_bytes(&[0xFF]).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(bytes, vec![0b1011_0101, 0b0111_1001, 0b1010_0000, 0xFF]);
+        let mut input = &bytes[..];
+        let mut reader = BitReader::new(&mut input);
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_bits(16).unwrap(), 0xABCD);
+        let mut byte = [0];
+        reader.read_bytes(&mut byte).unwrap();
+        assert_eq!(byte, [0xFF]);
+        assert!(reader.read_bit().is_err());
+    }
+}
@@ -0,0 +1,227 @@
+// lz77.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// LZSS, the variant of LZ77 that only emits a back-reference if it is shorter than
+// the bytes it replaces. Each item starts with a flag bit:
+//   0 followed by 8 bits: a literal byte
+//   1 followed by 12 bits distance and 4 bits length - MIN_MATCH: a copy of the bytes
+//     starting distance bytes back (distance 1..WINDOW_SIZE); distance 0 marks the end
+// Matches are found with hash chains over the first MIN_MATCH bytes.
+use super::{invalid_data, read_block, BitReader, BitWriter, Codec};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+pub const WINDOW_SIZE: usize = 1 << 12;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 15;
+const HASH_SIZE: usize = 1 << 12;
+// Longer chains find longer matches, but make compression slower
+const MAX_CHAIN: usize = 64;
+const NO_POSITION: usize = usize::MAX;
+
+pub struct Lzss;
+
+fn hash(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 8 ^ (bytes[1] as usize) << 4 ^ bytes[2] as usize) % HASH_SIZE
+}
+
+// Input not yet coded plus the window of already coded bytes before it.
+// Positions are absolute positions in the input; buffer starts at position base.
+struct Window<'a> {
+    input: &'a mut dyn Read,
+    buffer: Vec<u8>,
+    base: usize,
+    end_of_input: bool,
+    // latest position of each hash value, and for each position (modulo the window size)
+    // the previous position with the same hash
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl<'a> Window<'a> {
+    fn new(input: &'a mut dyn Read) -> Self {
+        Window {
+            input,
+            buffer: Vec::new(),
+            base: 0,
+            end_of_input: false,
+            head: vec![NO_POSITION; HASH_SIZE],
+            previous: vec![NO_POSITION; WINDOW_SIZE],
+        }
+    }
+
+    // Make sure MAX_MATCH bytes after position are buffered (unless the input ends),
+    // dropping bytes that left the window
+    fn fill(&mut self, position: usize) -> io::Result<()> {
+        if self.end_of_input || position + MAX_MATCH <= self.base + self.buffer.len() {
+            return Ok(());
+        }
+        if position - self.base > 2 * WINDOW_SIZE {
+            let drop = position - self.base - WINDOW_SIZE;
+            self.buffer.drain(..drop);
+            self.base += drop;
+        }
+        let mut block = vec![0; 4 * WINDOW_SIZE];
+        let read = read_block(self.input, &mut block)?;
+        self.end_of_input = read < block.len();
+        self.buffer.extend_from_slice(&block[..read]);
+        Ok(())
+    }
+
+    fn available(&self, position: usize) -> &[u8] {
+        &self.buffer[position - self.base..]
+    }
+
+    fn insert(&mut self, position: usize) {
+        let bytes = self.available(position);
+        if bytes.len() >= MIN_MATCH {
+            let hash = hash(bytes);
+            self.previous[position % WINDOW_SIZE] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    // (distance, length) of the longest earlier match within the window
+    fn longest_match(&self, position: usize) -> Option<(usize, usize)> {
+        let bytes = self.available(position);
+        if bytes.len() < MIN_MATCH {
+            return None;
+        }
+        let limit = bytes.len().min(MAX_MATCH);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[hash(bytes)];
+        for _ in 0..MAX_CHAIN {
+            // chains may lead to positions that left the window or were overwritten
+            if candidate == NO_POSITION
+                || candidate >= position
+                || position - candidate >= WINDOW_SIZE
+            {
+                break;
+            }
+            let length = self
+                .available(candidate)
+                .iter()
+                .zip(&bytes[..limit])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length >= MIN_MATCH && best.is_none_or(|(_, best_length)| length > best_length) {
+                best = Some((position - candidate, length));
+                if length == limit {
+                    break;
+                }
+            }
+            let next = self.previous[candidate % WINDOW_SIZE];
+            if next != NO_POSITION && next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        best
+    }
+}
+
+impl Codec for Lzss {
+    fn name(&self) -> &'static str {
+        "lz77"
+    }
+
+    fn encode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut output = BufWriter::new(output);
+        let mut writer = BitWriter::new(&mut output);
+        let mut window = Window::new(input);
+        let mut position = 0;
+        loop {
+            window.fill(position)?;
+            let Some(&byte) = window.available(position).first() else {
+                break;
+            };
+            match window.longest_match(position) {
+                Some((distance, length)) => {
+                    writer.write_bits(1, 1)?;
+                    writer.write_bits(distance as u32, 12)?;
+                    writer.write_bits((length - MIN_MATCH) as u32, 4)?;
+                    for offset in 0..length {
+                        window.insert(position + offset);
+                    }
+                    position += length;
+                }
+                None => {
+                    writer.write_bits(byte as u32, 9)?;
+                    window.insert(position);
+                    position += 1;
+                }
+            }
+        }
+        writer.write_bits(1, 1)?;
+        writer.write_bits(0, 16)?;
+        writer.finish()
+    }
+
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut input = BufReader::new(input);
+        let mut reader = BitReader::new(&mut input);
+        let mut output = BufWriter::new(output);
+        // the last WINDOW_SIZE bytes decoded (at least), written to output when dropped
+        let mut history: Vec<u8> = Vec::with_capacity(4 * WINDOW_SIZE);
+        loop {
+            if reader.read_bit()? == 0 {
+                history.push(reader.read_bits(8)? as u8);
+            } else {
+                let distance = reader.read_bits(12)? as usize;
+                let length = reader.read_bits(4)? as usize + MIN_MATCH;
+                if distance == 0 {
+                    break;
+                }
+                if distance > history.len() {
+                    return Err(invalid_data(
+                        "LZSS back-reference before the start of the data",
+                    ));
+                }
+                // byte by byte, as the copy may overlap the bytes it produces
+                let start = history.len() - distance;
+                for offset in 0..length {
+                    history.push(history[start + offset]);
+                }
+            }
+            if history.len() >= 3 * WINDOW_SIZE {
+                let written = history.len() - WINDOW_SIZE;
+                output.write_all(&history[..written])?;
+                history.drain(..written);
+            }
+        }
+        output.write_all(&history)?;
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_repetitions_by_back_references() {
+        // 3 literals, one back-reference of length 9 overlapping itself, end marker
+        let encoded = Lzss.encode_bytes(b"abcabcabcabc").unwrap();
+        assert_eq!(encoded.len(), (3 * 9 + 17 + 17usize).div_ceil(8));
+        assert_eq!(Lzss.decode_bytes(&encoded).unwrap(), b"abcabcabcabc");
+        let mut output = Vec::new();
+        let mut writer = BitWriter::new(&mut output);
+        writer.write_bits(1, 1).unwrap();
+        writer.write_bits(5, 12).unwrap();
+        writer.write_bits(0, 4).unwrap();
+        writer.finish().unwrap();
+        assert!(Lzss.decode_bytes(&output).is_err());
+    }
+}
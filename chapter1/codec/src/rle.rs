@@ -0,0 +1,112 @@
+// rle.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Run-length encoding in the style of PackBits: a control byte c < 128 is followed
+// by c + 1 bytes copied as they are, a control byte c >= 128 by one byte repeated c - 126 times.
+// Data without runs therefore grows by at most one byte in 128.
+use super::{read_block, Codec};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+const MAX_LITERALS: usize = 128;
+const MIN_RUN: usize = 3;
+const MAX_RUN: usize = 129;
+const BLOCK_SIZE: usize = 1 << 16;
+
+pub struct RunLength;
+
+fn run_length(data: &[u8]) -> usize {
+    data.iter()
+        .take(MAX_RUN)
+        .take_while(|&&byte| byte == data[0])
+        .count()
+}
+
+fn write_literals(output: &mut dyn Write, literals: &[u8]) -> io::Result<()> {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        output.write_all(&[(chunk.len() - 1) as u8])?;
+        output.write_all(chunk)?;
+    }
+    Ok(())
+}
+
+impl Codec for RunLength {
+    fn name(&self) -> &'static str {
+        "rle"
+    }
+
+    // Runs crossing the boundary of two blocks read from the input are coded as two runs
+    fn encode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut output = BufWriter::new(output);
+        let mut block = vec![0; BLOCK_SIZE];
+        loop {
+            let length = read_block(input, &mut block)?;
+            if length == 0 {
+                break;
+            }
+            let data = &block[..length];
+            let mut literals_start = 0;
+            let mut position = 0;
+            while position < data.len() {
+                let run = run_length(&data[position..]);
+                if run >= MIN_RUN {
+                    write_literals(&mut output, &data[literals_start..position])?;
+                    output.write_all(&[(run + 126) as u8, data[position]])?;
+                    position += run;
+                    literals_start = position;
+                } else {
+                    position += run;
+                }
+            }
+            write_literals(&mut output, &data[literals_start..])?;
+        }
+        output.flush()
+    }
+
+    fn decode(&self, input: &mut dyn Read, output: &mut dyn Write) -> io::Result<()> {
+        let mut input = BufReader::new(input);
+        let mut output = BufWriter::new(output);
+        let mut buffer = [0; MAX_RUN];
+        loop {
+            let mut control = [0];
+            if input.read(&mut control)? == 0 {
+                break;
+            }
+            let control = control[0] as usize;
+            if control < MAX_LITERALS {
+                input.read_exact(&mut buffer[..control + 1])?;
+                output.write_all(&buffer[..control + 1])?;
+            } else {
+                let mut byte = [0];
+                input.read_exact(&mut byte)?;
+                output.write_all(&[byte[0]; MAX_RUN][..control - 126])?;
+            }
+        }
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_runs_and_literals() {
+        let encoded = RunLength.encode_bytes(b"abcccccdd").unwrap();
+        assert_eq!(encoded, vec![1, b'a', b'b', 131, b'c', 1, b'd', b'd']);
+        let encoded = RunLength.encode_bytes(&[0; 300]).unwrap();
+        assert_eq!(encoded, vec![255, 0, 255, 0, 168, 0]);
+    }
+}
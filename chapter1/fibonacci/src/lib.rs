@@ -0,0 +1,324 @@
+// fibonacci/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Fibonacci numbers without the limits of fib1 to fib6: big integers instead of usize
+// (which overflows after fib(93)) and O(log n) algorithms without deep recursion.
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+
+// Fast doubling: F(2k) = F(k) * (2 F(k+1) - F(k)) and F(2k+1) = F(k)^2 + F(k+1)^2,
+// applied for the bits of n from the most significant one
+pub fn fib_fast_doubling(n: u64) -> BigUint {
+    let (mut a, mut b) = (BigUint::zero(), BigUint::one()); // F(k), F(k+1) for k = 0
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        let c = &a * (&b * 2u32 - &a);
+        let d = &a * &a + &b * &b;
+        (a, b) = if (n >> bit) & 1 == 0 {
+            (c, d)
+        } else {
+            let e = &c + &d;
+            (d, e)
+        };
+    }
+    a
+}
+
+// The same with all values reduced modulo m, e.g. for the last digits of huge Fibonacci numbers
+pub fn fib_mod(n: u64, m: u64) -> u64 {
+    assert!(m > 0, "modulus must be positive");
+    let m = u128::from(m);
+    let (mut a, mut b) = (0u128, 1 % m);
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        let c = a * ((2 * b + m - a) % m) % m;
+        let d = (a * a % m + b * b % m) % m;
+        (a, b) = if (n >> bit) & 1 == 0 {
+            (c, d)
+        } else {
+            (d, (c + d) % m)
+        };
+    }
+    a as u64
+}
+
+type Matrix<T> = [[T; 2]; 2];
+
+fn multiply<T>(x: &Matrix<T>, y: &Matrix<T>, reduce: impl Fn(T) -> T) -> Matrix<T>
+where
+    for<'a> &'a T: std::ops::Mul<&'a T, Output = T>,
+    T: std::ops::Add<Output = T>,
+{
+    // reducing each product keeps values modulo m < 2^64 from overflowing u128
+    let entry =
+        |i: usize, j: usize| reduce(reduce(&x[i][0] * &y[0][j]) + reduce(&x[i][1] * &y[1][j]));
+    [[entry(0, 0), entry(0, 1)], [entry(1, 0), entry(1, 1)]]
+}
+
+// [[1, 1], [1, 0]]^n = [[F(n+1), F(n)], [F(n), F(n-1)]], computed by repeated squaring
+fn fib_matrix_with<T>(n: u64, zero: T, one: T, reduce: impl Fn(T) -> T) -> T
+where
+    T: Clone,
+    for<'a> &'a T: std::ops::Mul<&'a T, Output = T>,
+    T: std::ops::Add<Output = T>,
+{
+    let mut result = [[one.clone(), zero.clone()], [zero.clone(), one.clone()]];
+    let mut power = [[one.clone(), one.clone()], [one, zero]];
+    let mut n = n;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = multiply(&result, &power, &reduce);
+        }
+        power = multiply(&power, &power, &reduce);
+        n >>= 1;
+    }
+    let [[_, fib], _] = result;
+    fib
+}
+
+pub fn fib_matrix(n: u64) -> BigUint {
+    fib_matrix_with(n, BigUint::zero(), BigUint::one(), |value| value)
+}
+
+pub fn fib_matrix_mod(n: u64, m: u64) -> u64 {
+    assert!(m > 0, "modulus must be positive");
+    let m = u128::from(m);
+    fib_matrix_with(n, 0u128, 1 % m, |value| value % m) as u64
+}
+
+// Like fib6's generator, but with big integers and without an upper limit;
+// use take(n) for the first n Fibonacci numbers
+pub struct FibonacciGenerator {
+    last: BigUint,
+    next: BigUint,
+}
+
+impl FibonacciGenerator {
+    pub fn new() -> Self {
+        FibonacciGenerator {
+            last: BigUint::zero(),
+            next: BigUint::one(),
+        }
+    }
+}
+
+impl Default for FibonacciGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for FibonacciGenerator {
+    type Item = BigUint;
+    fn next(&mut self) -> Option<Self::Item> {
+        let following = &self.last + &self.next;
+        let next = std::mem::replace(&mut self.next, following);
+        Some(std::mem::replace(&mut self.last, next))
+    }
+}
+
+// a(n) = coefficients[0] * a(n-1) + ... + coefficients[k-1] * a(n-k),
+// starting with a(0), ..., a(k-1) = initial, e.g. Fibonacci: coefficients [1, 1], initial [0, 1]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinearRecurrence {
+    coefficients: Vec<BigInt>,
+    initial: Vec<BigInt>,
+}
+
+// Square matrices of big integers, for the companion matrix of a recurrence
+fn matrix_product(
+    x: &[Vec<BigInt>],
+    y: &[Vec<BigInt>],
+    reduce: &impl Fn(BigInt) -> BigInt,
+) -> Vec<Vec<BigInt>> {
+    let size = x.len();
+    (0..size)
+        .map(|i| {
+            (0..size)
+                .map(|j| reduce((0..size).map(|l| &x[i][l] * &y[l][j]).sum()))
+                .collect()
+        })
+        .collect()
+}
+
+impl LinearRecurrence {
+    pub fn new(coefficients: Vec<BigInt>, initial: Vec<BigInt>) -> Self {
+        assert!(
+            !coefficients.is_empty() && coefficients.len() == initial.len(),
+            "a recurrence needs as many initial values as coefficients"
+        );
+        LinearRecurrence {
+            coefficients,
+            initial,
+        }
+    }
+    // Convenience constructor for small integer coefficients and initial values
+    pub fn from_i64(coefficients: &[i64], initial: &[i64]) -> Self {
+        LinearRecurrence::new(
+            coefficients.iter().map(|&c| BigInt::from(c)).collect(),
+            initial.iter().map(|&a| BigInt::from(a)).collect(),
+        )
+    }
+    pub fn fibonacci() -> Self {
+        LinearRecurrence::from_i64(&[1, 1], &[0, 1])
+    }
+    pub fn order(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    // The state vector (a(n+k-1), ..., a(n)) is the companion matrix to the n-th power
+    // times (a(k-1), ..., a(0)), so a(n) takes O(k^3 log n) big integer operations
+    fn nth_with(&self, n: u64, reduce: impl Fn(BigInt) -> BigInt) -> BigInt {
+        let k = self.order();
+        if n < k as u64 {
+            return reduce(self.initial[n as usize].clone());
+        }
+        let identity = |i: usize, j: usize| {
+            if i == j {
+                BigInt::one()
+            } else {
+                BigInt::zero()
+            }
+        };
+        let mut result: Vec<Vec<BigInt>> = (0..k)
+            .map(|i| (0..k).map(|j| identity(i, j)).collect())
+            .collect();
+        let mut power: Vec<Vec<BigInt>> = (0..k)
+            .map(|i| {
+                (0..k)
+                    .map(|j| {
+                        if i == 0 {
+                            reduce(self.coefficients[j].clone())
+                        } else {
+                            identity(i - 1, j)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut exponent = n - (k as u64 - 1);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = matrix_product(&result, &power, &reduce);
+            }
+            power = matrix_product(&power, &power, &reduce);
+            exponent >>= 1;
+        }
+        // first row of the result times (a(k-1), ..., a(0)) is a(n)
+        reduce(
+            (0..k)
+                .map(|j| &result[0][j] * &self.initial[k - 1 - j])
+                .sum(),
+        )
+    }
+    pub fn nth(&self, n: u64) -> BigInt {
+        self.nth_with(n, |value| value)
+    }
+    // a(n) modulo m, as a value in 0..m even for negative coefficients
+    pub fn nth_mod(&self, n: u64, m: u64) -> u64 {
+        assert!(m > 0, "modulus must be positive");
+        let modulus = BigInt::from(m);
+        let reduce = |value: BigInt| ((value % &modulus) + &modulus) % &modulus;
+        self.nth_with(n, reduce).to_u64().unwrap()
+    }
+    // All terms a(0), a(1), ... one after the other
+    pub fn iter(&self) -> RecurrenceIter<'_> {
+        RecurrenceIter {
+            recurrence: self,
+            window: self.initial.iter().cloned().collect(),
+        }
+    }
+}
+
+pub struct RecurrenceIter<'a> {
+    recurrence: &'a LinearRecurrence,
+    // the next k terms, oldest first
+    window: std::collections::VecDeque<BigInt>,
+}
+
+impl Iterator for RecurrenceIter<'_> {
+    type Item = BigInt;
+    fn next(&mut self) -> Option<BigInt> {
+        let following: BigInt = self
+            .recurrence
+            .coefficients
+            .iter()
+            .zip(self.window.iter().rev())
+            .map(|(coefficient, term)| coefficient * term)
+            .sum();
+        self.window.push_back(following);
+        self.window.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_methods_agree_beyond_usize() {
+        let expected: BigUint = "354224848179261915075".parse().unwrap();
+        assert_eq!(fib_fast_doubling(100), expected);
+        assert_eq!(fib_matrix(100), expected);
+        assert_eq!(
+            LinearRecurrence::fibonacci().nth(100),
+            BigInt::from(expected)
+        );
+        for (n, fib) in FibonacciGenerator::new().take(300).enumerate() {
+            assert_eq!(fib_fast_doubling(n as u64), fib);
+            assert_eq!(fib_matrix(n as u64), fib);
+            let m = 1_000_000_007u64;
+            let reduced = (&fib % m).to_u64().unwrap();
+            assert_eq!(fib_mod(n as u64, m), reduced);
+            assert_eq!(fib_matrix_mod(n as u64, m), reduced);
+        }
+        assert_eq!(fib_fast_doubling(10_000).to_string().len(), 2090);
+    }
+
+    #[test]
+    fn modular_fibonacci_for_huge_n() {
+        // the last digits repeat with the Pisano period 60
+        assert_eq!(fib_mod(u64::MAX, 10), fib_mod(u64::MAX % 60, 10));
+        assert_eq!(fib_matrix_mod(u64::MAX, 10), fib_mod(u64::MAX, 10));
+        assert_eq!(fib_mod(5, 1), 0);
+        let large_modulus = u64::MAX - 58; // the largest 64 bit prime
+        assert_eq!(
+            fib_mod(1000, large_modulus),
+            fib_matrix_mod(1000, large_modulus)
+        );
+        assert_eq!(
+            BigUint::from(fib_mod(1000, large_modulus)),
+            fib_fast_doubling(1000) % large_modulus
+        );
+    }
+
+    #[test]
+    fn general_linear_recurrences() {
+        let lucas = LinearRecurrence::from_i64(&[1, 1], &[2, 1]);
+        let terms: Vec<BigInt> = lucas.iter().take(8).collect();
+        assert_eq!(terms, [2, 1, 3, 4, 7, 11, 18, 29].map(BigInt::from));
+        let tribonacci = LinearRecurrence::from_i64(&[1, 1, 1], &[0, 0, 1]);
+        for (n, term) in tribonacci.iter().take(60).enumerate() {
+            assert_eq!(tribonacci.nth(n as u64), term);
+            assert_eq!(BigInt::from(tribonacci.nth_mod(n as u64, 97)), term % 97);
+        }
+        // a(n) = 2 a(n-1) - a(n-2) with a(0) = 5, a(1) = 2 decreases by 3 per step
+        let arithmetic = LinearRecurrence::from_i64(&[2, -1], &[5, 2]);
+        assert_eq!(arithmetic.nth(1000), BigInt::from(5 - 3 * 1000));
+        assert_eq!(
+            arithmetic.nth_mod(1000, 7),
+            (5i64 - 3000).rem_euclid(7) as u64
+        );
+    }
+}
@@ -0,0 +1,375 @@
+// genome/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// A genome stored with 2 bits per nucleotide (A = 00, C = 01, G = 10, T = 11),
+// 32 nucleotides per u64 word. Nucleotide i is stored in word i / 32 at bit 2 * (i % 32).
+// Bits after the last nucleotide are always zero, so genomes can be compared word by word.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+const NUCLEOTIDES_PER_WORD: usize = 32;
+// File format: magic, version byte, number of nucleotides (u64), then the words (u64),
+// all little-endian
+const MAGIC: &[u8; 4] = b"CGNM";
+const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum GenomeError {
+    // A character that is not A, C, G or T (in either case) at the given index
+    InvalidNucleotide { found: char, index: usize },
+    // A file that is not a compressed genome or is damaged
+    InvalidFormat(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeError::InvalidNucleotide { found, index } => {
+                write!(formatter, "invalid nucleotide {found:?} at index {index}")
+            }
+            GenomeError::InvalidFormat(message) => {
+                write!(formatter, "invalid compressed genome file: {message}")
+            }
+            GenomeError::Io(error) => write!(formatter, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GenomeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GenomeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GenomeError {
+    fn from(error: io::Error) -> Self {
+        GenomeError::Io(error)
+    }
+}
+
+fn encode(nucleotide: char) -> Option<u64> {
+    match nucleotide.to_ascii_uppercase() {
+        'A' => Some(0b00),
+        'C' => Some(0b01),
+        'G' => Some(0b10),
+        'T' => Some(0b11),
+        _ => None,
+    }
+}
+
+fn decode(bits: u64) -> char {
+    ['A', 'C', 'G', 'T'][bits as usize & 0b11]
+}
+
+fn words_for(len: usize) -> usize {
+    len.div_ceil(NUCLEOTIDES_PER_WORD)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CompressedGenome {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl CompressedGenome {
+    pub fn new(genome: &str) -> Result<Self, GenomeError> {
+        let mut compressed = CompressedGenome::with_capacity(genome.len());
+        for (index, nucleotide) in genome.chars().enumerate() {
+            compressed
+                .push(nucleotide)
+                .map_err(|_| GenomeError::InvalidNucleotide {
+                    found: nucleotide,
+                    index,
+                })?;
+        }
+        Ok(compressed)
+    }
+    pub fn with_capacity(nucleotides: usize) -> Self {
+        CompressedGenome {
+            words: Vec::with_capacity(words_for(nucleotides)),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    // Heap memory used for the nucleotides, a quarter of the ASCII text (rounded up to whole words)
+    pub fn size_in_bytes(&self) -> usize {
+        self.words.len() * std::mem::size_of::<u64>()
+    }
+
+    fn bits(&self, index: usize) -> u64 {
+        let shift = 2 * (index % NUCLEOTIDES_PER_WORD);
+        (self.words[index / NUCLEOTIDES_PER_WORD] >> shift) & 0b11
+    }
+    fn push_bits(&mut self, bits: u64) {
+        let offset = self.len % NUCLEOTIDES_PER_WORD;
+        if offset == 0 {
+            self.words.push(bits);
+        } else {
+            *self.words.last_mut().unwrap() |= bits << (2 * offset);
+        }
+        self.len += 1;
+    }
+
+    pub fn push(&mut self, nucleotide: char) -> Result<(), GenomeError> {
+        let bits = encode(nucleotide).ok_or(GenomeError::InvalidNucleotide {
+            found: nucleotide,
+            index: self.len,
+        })?;
+        self.push_bits(bits);
+        Ok(())
+    }
+    pub fn get(&self, index: usize) -> Option<char> {
+        (index < self.len).then(|| decode(self.bits(index)))
+    }
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            genome: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+    pub fn decompress(&self) -> String {
+        self.iter().collect()
+    }
+
+    // Copy of the nucleotides in range, shifting whole words instead of single nucleotides
+    pub fn slice(&self, range: Range<usize>) -> CompressedGenome {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {range:?} out of bounds for genome of length {}",
+            self.len
+        );
+        let len = range.end - range.start;
+        let first_word = range.start / NUCLEOTIDES_PER_WORD;
+        let shift = 2 * (range.start % NUCLEOTIDES_PER_WORD);
+        let mut words: Vec<u64> = (0..words_for(len))
+            .map(|index| {
+                let low = self.words[first_word + index] >> shift;
+                let high = match self.words.get(first_word + index + 1) {
+                    Some(word) if shift > 0 => word << (64 - shift),
+                    _ => 0,
+                };
+                low | high
+            })
+            .collect();
+        let used = 2 * (len % NUCLEOTIDES_PER_WORD);
+        if let (Some(last), true) = (words.last_mut(), used > 0) {
+            *last &= (1 << used) - 1;
+        }
+        CompressedGenome { words, len }
+    }
+
+    // The complement of a nucleotide is 3 minus its code (A <-> T, C <-> G)
+    pub fn reverse_complement(&self) -> CompressedGenome {
+        let mut result = CompressedGenome::with_capacity(self.len);
+        for index in (0..self.len).rev() {
+            result.push_bits(0b11 ^ self.bits(index));
+        }
+        result
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), GenomeError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&(self.len as u64).to_le_bytes())?;
+        for word in &self.words {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+    pub fn read_from(mut reader: impl Read) -> Result<Self, GenomeError> {
+        let mut header = [0; 13];
+        read_exact(&mut reader, &mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(GenomeError::InvalidFormat("wrong magic number".to_string()));
+        }
+        if header[4] != VERSION {
+            return Err(GenomeError::InvalidFormat(format!(
+                "unsupported version {}",
+                header[4]
+            )));
+        }
+        let len = u64::from_le_bytes(header[5..13].try_into().unwrap());
+        let len = usize::try_from(len)
+            .map_err(|_| GenomeError::InvalidFormat(format!("length {len} too large")))?;
+        let mut words = Vec::new();
+        let mut buffer = [0; 8];
+        for _ in 0..words_for(len) {
+            read_exact(&mut reader, &mut buffer)?;
+            words.push(u64::from_le_bytes(buffer));
+        }
+        let used = 2 * (len % NUCLEOTIDES_PER_WORD);
+        if used > 0 && words.last().is_some_and(|last| last >> used != 0) {
+            return Err(GenomeError::InvalidFormat(
+                "bits set after the last nucleotide".to_string(),
+            ));
+        }
+        Ok(CompressedGenome { words, len })
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GenomeError> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GenomeError> {
+        CompressedGenome::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+// A file ending too early is a format error rather than an I/O error
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), GenomeError> {
+    reader.read_exact(buffer).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            GenomeError::InvalidFormat("file is truncated".to_string())
+        } else {
+            GenomeError::Io(error)
+        }
+    })
+}
+
+impl FromStr for CompressedGenome {
+    type Err = GenomeError;
+    fn from_str(genome: &str) -> Result<Self, Self::Err> {
+        CompressedGenome::new(genome)
+    }
+}
+
+impl fmt::Display for CompressedGenome {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for nucleotide in self.iter() {
+            write!(formatter, "{nucleotide}")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Iter<'a> {
+    genome: &'a CompressedGenome,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(decode(self.genome.bits(self.front - 1)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.back - self.front, Some(self.back - self.front))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<char> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(decode(self.genome.bits(self.back)))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a CompressedGenome {
+    type Item = char;
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENOME: &str =
+        "TAGGGATTAACCGTTATATATATATAGCCATGGATCGATTATATAGGGATTAACCGTTATATATATATAGCCATGGATCGATTATA";
+
+    #[test]
+    fn compresses_to_two_bits_per_nucleotide() {
+        let genome = CompressedGenome::new(GENOME).unwrap();
+        assert_eq!(genome.len(), GENOME.len());
+        assert_eq!(genome.size_in_bytes(), 24);
+        assert_eq!(genome.decompress(), GENOME);
+        assert_eq!(genome.to_string(), GENOME);
+        assert_eq!(genome.get(2), Some('G'));
+        assert_eq!(genome.get(GENOME.len()), None);
+        assert_eq!(genome.iter().next_back(), Some('A'));
+        assert_eq!(genome.iter().len(), GENOME.len());
+        assert_eq!(
+            "acgt".parse::<CompressedGenome>().unwrap().to_string(),
+            "ACGT"
+        );
+        assert!(matches!(
+            CompressedGenome::new("ACGTN"),
+            Err(GenomeError::InvalidNucleotide {
+                found: 'N',
+                index: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn slices_and_reverse_complements() {
+        let genome = CompressedGenome::new(GENOME).unwrap();
+        for (start, end) in [(0, 0), (0, 86), (3, 40), (31, 33), (32, 64), (45, 86)] {
+            let slice = genome.slice(start..end);
+            assert_eq!(slice.to_string(), GENOME[start..end]);
+            assert_eq!(slice, CompressedGenome::new(&GENOME[start..end]).unwrap());
+        }
+        let reverse = CompressedGenome::new("AACGTTG")
+            .unwrap()
+            .reverse_complement();
+        assert_eq!(reverse.to_string(), "CAACGTT");
+        assert_eq!(genome.reverse_complement().reverse_complement(), genome);
+    }
+
+    #[test]
+    fn file_format_round_trips() {
+        let genome = CompressedGenome::new(GENOME).unwrap();
+        let mut bytes = Vec::new();
+        genome.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 13 + 24);
+        assert_eq!(CompressedGenome::read_from(&bytes[..]).unwrap(), genome);
+        assert!(matches!(
+            CompressedGenome::read_from(&bytes[..20]),
+            Err(GenomeError::InvalidFormat(_))
+        ));
+        bytes[0] = b'X';
+        assert!(matches!(
+            CompressedGenome::read_from(&bytes[..]),
+            Err(GenomeError::InvalidFormat(_))
+        ));
+    }
+}
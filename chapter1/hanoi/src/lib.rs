@@ -0,0 +1,468 @@
+// hanoi/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Towers of Hanoi with any number of discs and pegs. Discs are numbered from 1 (the smallest)
+// to n, pegs from 0 to k - 1, and all discs move from peg 0 to peg k - 1.
+// The moves are produced lazily, so even towers with billions of moves can be streamed.
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub disc: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "disc {} from {} to {}",
+            self.disc, self.from, self.to
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HanoiError {
+    // Move number step (counting from 1) refers to a peg that does not exist
+    PegOutOfRange {
+        step: u64,
+        peg: usize,
+    },
+    // Move number step takes a disc from an empty peg
+    EmptyPeg {
+        step: u64,
+        peg: usize,
+    },
+    // Move number step names a disc that is not on top of its peg
+    WrongDisc {
+        step: u64,
+        expected: usize,
+        found: usize,
+    },
+    // Move number step puts a disc on a smaller one
+    LargerOnSmaller {
+        step: u64,
+        disc: usize,
+        onto: usize,
+    },
+    // All moves are legal, but not all discs ended up on the last peg
+    NotSolved,
+}
+
+impl fmt::Display for HanoiError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HanoiError::PegOutOfRange { step, peg } => {
+                write!(formatter, "move {step}: there is no peg {peg}")
+            }
+            HanoiError::EmptyPeg { step, peg } => {
+                write!(formatter, "move {step}: peg {peg} is empty")
+            }
+            HanoiError::WrongDisc {
+                step,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "move {step}: disc {found} is not on top, disc {expected} is"
+            ),
+            HanoiError::LargerOnSmaller { step, disc, onto } => {
+                write!(
+                    formatter,
+                    "move {step}: disc {disc} cannot go on disc {onto}"
+                )
+            }
+            HanoiError::NotSolved => write!(formatter, "the discs are not all on the last peg"),
+        }
+    }
+}
+
+impl std::error::Error for HanoiError {}
+
+// Minimal number of moves for n discs and k pegs (Frame-Stewart) together with the number
+// of discs to park on an intermediate peg, for all n <= discs and 3 <= k <= pegs
+struct FrameStewart {
+    // costs[k][n] and splits[k][n]; counts too large for u128 saturate
+    costs: Vec<Vec<u128>>,
+    splits: Vec<Vec<usize>>,
+}
+
+impl FrameStewart {
+    fn new(discs: usize, pegs: usize) -> Self {
+        let mut costs = vec![Vec::new(); pegs + 1];
+        let mut splits = vec![Vec::new(); pegs + 1];
+        // with 3 pegs the n - 1 smaller discs have to be parked: 2^n - 1 moves
+        costs[3] = (0..=discs)
+            .map(|n| {
+                1u128
+                    .checked_shl(n as u32)
+                    .map_or(u128::MAX, |power| power - 1)
+            })
+            .collect();
+        splits[3] = (0..=discs).map(|n| n.saturating_sub(1)).collect();
+        for k in 4..=pegs {
+            costs[k] = vec![0, 1];
+            splits[k] = vec![0, 0];
+            for n in 2..=discs {
+                // park m discs with k pegs, move the other n - m with k - 1 pegs, unpark
+                let (cost, split) = (1..n)
+                    .map(|m| {
+                        let cost = costs[k][m]
+                            .saturating_mul(2)
+                            .saturating_add(costs[k - 1][n - m]);
+                        (cost, m)
+                    })
+                    .min()
+                    .unwrap();
+                costs[k].push(cost);
+                splits[k].push(split);
+            }
+            costs[k].truncate(discs + 1);
+            splits[k].truncate(discs + 1);
+        }
+        FrameStewart { costs, splits }
+    }
+}
+
+// Number of moves of the solution for the given discs and pegs (saturating at u128::MAX)
+pub fn move_count(discs: usize, pegs: usize) -> u128 {
+    assert!(pegs >= 3, "Towers of Hanoi need at least 3 pegs");
+    FrameStewart::new(discs, pegs).costs[pegs][discs]
+}
+
+// Move the discs smallest..smallest + count from one peg to another using only the
+// pegs in the bit set available
+struct Tower {
+    smallest: usize,
+    count: usize,
+    from: usize,
+    to: usize,
+    available: u64,
+}
+
+// The moves of the Frame-Stewart solution (the optimal one for 3 pegs), one after the other.
+// Instead of recursion, the sub-towers still to be moved are kept on an explicit stack.
+pub struct Moves {
+    frame_stewart: FrameStewart,
+    tasks: Vec<Tower>,
+    remaining: u128,
+}
+
+impl Moves {
+    pub fn new(discs: usize, pegs: usize) -> Self {
+        assert!(
+            (3..=64).contains(&pegs),
+            "Towers of Hanoi need between 3 and 64 pegs"
+        );
+        let frame_stewart = FrameStewart::new(discs, pegs);
+        let remaining = frame_stewart.costs[pegs][discs];
+        Moves {
+            frame_stewart,
+            tasks: vec![Tower {
+                smallest: 1,
+                count: discs,
+                from: 0,
+                to: pegs - 1,
+                available: u64::MAX >> (64 - pegs),
+            }],
+            remaining,
+        }
+    }
+}
+
+impl Iterator for Moves {
+    type Item = Move;
+    fn next(&mut self) -> Option<Move> {
+        while let Some(Tower {
+            smallest,
+            count,
+            from,
+            to,
+            available,
+        }) = self.tasks.pop()
+        {
+            if count == 0 {
+                continue;
+            }
+            if count == 1 {
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some(Move {
+                    disc: smallest,
+                    from,
+                    to,
+                });
+            }
+            let pegs = available.count_ones() as usize;
+            let parked = self.frame_stewart.splits[pegs][count];
+            let others = available & !(1 << from) & !(1 << to);
+            let via = others.trailing_zeros() as usize;
+            // pushed in reverse order: park, move the rest without the parking peg, unpark
+            self.tasks.push(Tower {
+                smallest,
+                count: parked,
+                from: via,
+                to,
+                available,
+            });
+            self.tasks.push(Tower {
+                smallest: smallest + parked,
+                count: count - parked,
+                from,
+                to,
+                available: available & !(1 << via),
+            });
+            self.tasks.push(Tower {
+                smallest,
+                count: parked,
+                from,
+                to: via,
+                available,
+            });
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+// The classic iterative solution for 3 pegs: move i (from 1) moves the disc given by the
+// trailing zeros of i, so no state but the move number is needed
+pub struct IterativeMoves {
+    discs: usize,
+    step: u64,
+    total: u64,
+}
+
+impl IterativeMoves {
+    pub fn new(discs: usize) -> Self {
+        assert!(discs < 64, "the iterative solver handles at most 63 discs");
+        IterativeMoves {
+            discs,
+            step: 0,
+            total: (1 << discs) - 1,
+        }
+    }
+}
+
+impl Iterator for IterativeMoves {
+    type Item = Move;
+    fn next(&mut self) -> Option<Move> {
+        if self.step == self.total {
+            return None;
+        }
+        self.step += 1;
+        let i = self.step;
+        // these formulas end on peg 2 for an odd number of discs and on peg 1 otherwise
+        let peg = |peg: u64| {
+            let peg = (peg % 3) as usize;
+            if self.discs.is_multiple_of(2) && peg != 0 {
+                3 - peg
+            } else {
+                peg
+            }
+        };
+        Some(Move {
+            disc: i.trailing_zeros() as usize + 1,
+            from: peg(i & (i - 1)),
+            to: peg((i | (i - 1)) + 1),
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.total - self.step) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+// The discs on each peg, bottom first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Towers {
+    pegs: Vec<Vec<usize>>,
+}
+
+impl Towers {
+    // All discs on peg 0
+    pub fn new(discs: usize, pegs: usize) -> Self {
+        let mut towers = vec![Vec::new(); pegs];
+        towers[0] = (1..=discs).rev().collect();
+        Towers { pegs: towers }
+    }
+    pub fn peg(&self, peg: usize) -> &[usize] {
+        &self.pegs[peg]
+    }
+    // Carries out a move if it is legal; step is only used for error messages
+    pub fn apply(&mut self, step: u64, next: &Move) -> Result<(), HanoiError> {
+        for peg in [next.from, next.to] {
+            if peg >= self.pegs.len() {
+                return Err(HanoiError::PegOutOfRange { step, peg });
+            }
+        }
+        let top = *self.pegs[next.from].last().ok_or(HanoiError::EmptyPeg {
+            step,
+            peg: next.from,
+        })?;
+        if top != next.disc {
+            return Err(HanoiError::WrongDisc {
+                step,
+                expected: top,
+                found: next.disc,
+            });
+        }
+        if let Some(&onto) = self.pegs[next.to].last() {
+            if onto < top {
+                return Err(HanoiError::LargerOnSmaller {
+                    step,
+                    disc: top,
+                    onto,
+                });
+            }
+        }
+        self.pegs[next.from].pop();
+        self.pegs[next.to].push(top);
+        Ok(())
+    }
+    // All discs on the last peg
+    pub fn is_solved(&self) -> bool {
+        self.pegs[..self.pegs.len() - 1]
+            .iter()
+            .all(|peg| peg.is_empty())
+    }
+}
+
+impl fmt::Display for Towers {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, peg) in self.pegs.iter().enumerate() {
+            writeln!(formatter, "Peg {index}: {peg:?}")?;
+        }
+        Ok(())
+    }
+}
+
+// Replays the moves from the start position and checks each for legality and that they
+// solve the puzzle, returning the number of moves
+pub fn verify(
+    discs: usize,
+    pegs: usize,
+    moves: impl IntoIterator<Item = Move>,
+) -> Result<u64, HanoiError> {
+    let mut towers = Towers::new(discs, pegs);
+    let mut steps = 0;
+    for next in moves {
+        steps += 1;
+        towers.apply(steps, &next)?;
+    }
+    if towers.is_solved() {
+        Ok(steps)
+    } else {
+        Err(HanoiError::NotSolved)
+    }
+}
+
+// Writes the puzzle and its moves as JSON for visualisation tools, e.g.
+// {"discs": 2, "pegs": 3, "moves": [[1, 0, 1], [2, 0, 2], [1, 1, 2]]}
+// with each move as [disc, from, to]; the moves are streamed, not collected first
+pub fn write_json(
+    discs: usize,
+    pegs: usize,
+    moves: impl IntoIterator<Item = Move>,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "{{\"discs\": {discs}, \"pegs\": {pegs}, \"moves\": ["
+    )?;
+    for (index, next) in moves.into_iter().enumerate() {
+        if index > 0 {
+            write!(writer, ", ")?;
+        }
+        write!(writer, "[{}, {}, {}]", next.disc, next.from, next.to)?;
+    }
+    writeln!(writer, "]}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solutions_are_legal_and_minimal() {
+        for discs in 0..=10 {
+            assert_eq!(verify(discs, 3, Moves::new(discs, 3)), Ok((1 << discs) - 1));
+            assert_eq!(
+                verify(discs, 3, IterativeMoves::new(discs)),
+                Ok((1 << discs) - 1)
+            );
+            assert!(Moves::new(discs, 3).eq(IterativeMoves::new(discs)));
+        }
+        // known Frame-Stewart numbers for 4 and 5 pegs
+        let four_pegs = [0, 1, 3, 5, 9, 13, 17, 25, 33, 41, 49, 65, 81];
+        for (discs, &count) in four_pegs.iter().enumerate() {
+            assert_eq!(move_count(discs, 4), count);
+            assert_eq!(verify(discs, 4, Moves::new(discs, 4)), Ok(count as u64));
+        }
+        assert_eq!(move_count(10, 5), 31);
+        assert_eq!(verify(10, 5, Moves::new(10, 5)), Ok(31));
+        assert_eq!(
+            verify(20, 7, Moves::new(20, 7)),
+            Ok(move_count(20, 7) as u64)
+        );
+        assert_eq!(Moves::new(12, 4).size_hint(), (81, Some(81)));
+        assert_eq!(move_count(200, 3), u128::MAX);
+    }
+
+    #[test]
+    fn verifier_rejects_illegal_moves() {
+        let mv = |disc, from, to| Move { disc, from, to };
+        assert_eq!(
+            verify(2, 3, [mv(2, 0, 1)]),
+            Err(HanoiError::WrongDisc {
+                step: 1,
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            verify(2, 3, [mv(1, 0, 1), mv(2, 0, 1)]),
+            Err(HanoiError::LargerOnSmaller {
+                step: 2,
+                disc: 2,
+                onto: 1
+            })
+        );
+        assert_eq!(
+            verify(2, 3, [mv(1, 2, 1)]),
+            Err(HanoiError::EmptyPeg { step: 1, peg: 2 })
+        );
+        assert_eq!(
+            verify(2, 3, [mv(1, 0, 3)]),
+            Err(HanoiError::PegOutOfRange { step: 1, peg: 3 })
+        );
+        assert_eq!(verify(2, 3, [mv(1, 0, 2)]), Err(HanoiError::NotSolved));
+
+        let mut json = Vec::new();
+        write_json(2, 3, Moves::new(2, 3), &mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"discs\": 2, \"pegs\": 3, \"moves\": [[1, 0, 1], [2, 0, 2], [1, 1, 2]]}\n"
+        );
+    }
+}
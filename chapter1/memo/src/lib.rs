@@ -0,0 +1,257 @@
+// memo/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Memoisation as in fib3 (a hand-rolled HashMap) and fib4 (the memoize macro), but reusable:
+// functions with several arguments use a tuple as key, the number of results kept can be
+// limited and hits and misses are counted.
+//
+// A recursive function passes the memo on to its recursive calls:
+//
+// fn fib(n: u64, memo: &mut Memo<u64, u64>) -> u64 {
+//     memo.get_or_insert_with(n, |memo| {
+//         if n < 2 { n } else { fib(n - 1, memo) + fib(n - 2, memo) }
+//     })
+// }
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    // Keep every result, like fib3 and fib4
+    Unbounded,
+    // Keep at most the given number of results, dropping the least recently used one
+    Lru(usize),
+    // Keep at most the given number of results, dropping the one stored first
+    SizeCapped(usize),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl Stats {
+    // Fraction of lookups answered from the memo, 0 if there were none
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} hits, {} misses, {} evictions (hit ratio {:.1}%)",
+            self.hits,
+            self.misses,
+            self.evictions,
+            100.0 * self.hit_ratio()
+        )
+    }
+}
+
+pub struct Memo<K, V> {
+    policy: Policy,
+    // the value and its position in order
+    entries: HashMap<K, (V, u64)>,
+    // keys by last use (Lru) or by insertion (SizeCapped), oldest first
+    order: BTreeMap<u64, K>,
+    clock: u64,
+    stats: Stats,
+}
+
+impl<K, V> Memo<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    pub fn new(policy: Policy) -> Self {
+        if let Policy::Lru(capacity) | Policy::SizeCapped(capacity) = policy {
+            assert!(capacity > 0, "a memo needs room for at least one result");
+        }
+        Memo {
+            policy,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            stats: Stats::default(),
+        }
+    }
+    pub fn unbounded() -> Self {
+        Memo::new(Policy::Unbounded)
+    }
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+    // Looks at a stored result without counting a lookup or changing the LRU order
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    // The stored result for key (a hit) or the one calculated by compute (a miss).
+    // compute gets the memo itself, so that it can make memoised recursive calls.
+    pub fn get_or_insert_with(&mut self, key: K, compute: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(value) = self.lookup(&key) {
+            self.stats.hits += 1;
+            return value;
+        }
+        self.stats.misses += 1;
+        let value = compute(self);
+        self.insert(key, value.clone());
+        value
+    }
+
+    fn lookup(&mut self, key: &K) -> Option<V> {
+        let clock = self.clock;
+        let (value, position) = self.entries.get_mut(key)?;
+        if let Policy::Lru(_) = self.policy {
+            let key = self.order.remove(position).unwrap();
+            *position = clock;
+            self.order.insert(clock, key);
+            self.clock += 1;
+        }
+        Some(value.clone())
+    }
+
+    // Stores a result, e.g. a base case, replacing an earlier one for the same key
+    pub fn insert(&mut self, key: K, value: V) {
+        let position = self.clock;
+        self.clock += 1;
+        if let Some((_, old_position)) = self.entries.insert(key.clone(), (value, position)) {
+            self.order.remove(&old_position);
+        }
+        match self.policy {
+            Policy::Unbounded => {}
+            Policy::Lru(capacity) | Policy::SizeCapped(capacity) => {
+                self.order.insert(position, key);
+                while self.entries.len() > capacity {
+                    let (_, oldest) = self.order.pop_first().unwrap();
+                    self.entries.remove(&oldest);
+                    self.stats.evictions += 1;
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> Default for Memo<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Memo::unbounded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fib(n: u64, memo: &mut Memo<u64, u64>) -> u64 {
+        memo.get_or_insert_with(n, |memo| {
+            if n < 2 {
+                n
+            } else {
+                fib(n - 1, memo) + fib(n - 2, memo)
+            }
+        })
+    }
+
+    // Binomial coefficients: a function with two arguments
+    fn choose(n: u64, k: u64, memo: &mut Memo<(u64, u64), u64>) -> u64 {
+        memo.get_or_insert_with((n, k), |memo| {
+            if k == 0 || k == n {
+                1
+            } else {
+                choose(n - 1, k - 1, memo) + choose(n - 1, k, memo)
+            }
+        })
+    }
+
+    #[test]
+    fn memoises_recursive_functions() {
+        let mut memo = Memo::unbounded();
+        assert_eq!(fib(50, &mut memo), 12_586_269_025);
+        assert_eq!(memo.len(), 51);
+        assert_eq!(
+            memo.stats(),
+            Stats {
+                hits: 48,
+                misses: 51,
+                evictions: 0
+            }
+        );
+        fib(50, &mut memo);
+        assert_eq!(memo.stats().hits, 49);
+
+        let mut memo = Memo::default();
+        assert_eq!(choose(30, 15, &mut memo), 155_117_520);
+        assert!(memo.stats().hit_ratio() > 0.4);
+        assert_eq!(memo.peek(&(4, 2)), Some(&6));
+    }
+
+    #[test]
+    fn bounded_policies_evict() {
+        // fib only needs the last two results, so a tiny LRU memo still avoids exponential time
+        let mut memo = Memo::new(Policy::Lru(3));
+        assert_eq!(fib(80, &mut memo), 23_416_728_348_467_685);
+        assert_eq!(memo.len(), 3);
+        assert_eq!(memo.stats().misses, 81);
+
+        let mut memo = Memo::new(Policy::Lru(2));
+        memo.insert(1, 'a');
+        memo.insert(2, 'b');
+        memo.get_or_insert_with(1, |_| unreachable!());
+        memo.insert(3, 'c');
+        assert_eq!((memo.peek(&1), memo.peek(&2)), (Some(&'a'), None));
+
+        let mut memo = Memo::new(Policy::SizeCapped(2));
+        memo.insert(1, 'a');
+        memo.insert(2, 'b');
+        memo.get_or_insert_with(1, |_| unreachable!());
+        memo.insert(3, 'c');
+        assert_eq!((memo.peek(&1), memo.peek(&2)), (None, Some(&'b')));
+        assert_eq!(memo.stats().evictions, 1);
+        memo.insert(2, 'B');
+        assert_eq!(memo.peek(&2), Some(&'B'));
+        assert_eq!(memo.len(), 2);
+    }
+}
@@ -0,0 +1,394 @@
+// otp/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// One-time pad encryption of arbitrary byte streams with keys stored in key files.
+// A one-time pad is only unbreakable if the key is random, at least as long as the data
+// and never used twice, so key files record whether they have been used for encryption.
+//
+// Key file: "OTPK", version, used flag, key id (16 random bytes),
+//   key length (u64, little-endian), key bytes
+// Encrypted file: "OTPC", version, key id, data XOR key, then a trailer of
+//   data length (u64) and FNV-1a checksum of the data (u64), XOR the next 16 key bytes.
+// The encrypted checksum detects damaged files and wrong keys; it is not a cryptographic MAC.
+use rand::RngCore;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const KEY_MAGIC: &[u8; 4] = b"OTPK";
+const CIPHER_MAGIC: &[u8; 4] = b"OTPC";
+const VERSION: u8 = 1;
+const USED_FLAG_OFFSET: u64 = 5;
+const KEY_HEADER_SIZE: usize = 4 + 1 + 1 + 16 + 8;
+const CIPHER_HEADER_SIZE: usize = 4 + 1 + 16;
+const TRAILER_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 1 << 16;
+
+#[derive(Debug)]
+pub enum OtpError {
+    KeyAlreadyUsed,
+    // The key has fewer bytes than the data plus trailer need
+    KeyTooShort { available: u64 },
+    // The data was encrypted with another key
+    WrongKey,
+    InvalidFormat(String),
+    IntegrityCheckFailed,
+    Io(io::Error),
+}
+
+impl fmt::Display for OtpError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OtpError::KeyAlreadyUsed => {
+                write!(formatter, "key has already been used for encryption")
+            }
+            OtpError::KeyTooShort { available } => write!(
+                formatter,
+                "key of {available} bytes is too short (data length + {TRAILER_SIZE} bytes needed)"
+            ),
+            OtpError::WrongKey => write!(formatter, "data was encrypted with another key"),
+            OtpError::InvalidFormat(message) => write!(formatter, "invalid file: {message}"),
+            OtpError::IntegrityCheckFailed => {
+                write!(
+                    formatter,
+                    "decrypted data is damaged (length or checksum mismatch)"
+                )
+            }
+            OtpError::Io(error) => write!(formatter, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for OtpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OtpError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for OtpError {
+    fn from(error: io::Error) -> Self {
+        OtpError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub id: [u8; 16],
+    pub length: u64,
+    pub used: bool,
+}
+
+fn read_exact(
+    reader: &mut (impl Read + ?Sized),
+    buffer: &mut [u8],
+    what: &str,
+) -> Result<(), OtpError> {
+    reader.read_exact(buffer).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            OtpError::InvalidFormat(format!("{what} is truncated"))
+        } else {
+            OtpError::Io(error)
+        }
+    })
+}
+
+// Create a new key file with length random bytes; existing files are not overwritten
+pub fn generate_key_file(path: impl AsRef<Path>, length: u64) -> Result<KeyInfo, OtpError> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let mut rng = rand::rng();
+    let mut id = [0; 16];
+    rng.fill_bytes(&mut id);
+    file.write_all(KEY_MAGIC)?;
+    file.write_all(&[VERSION, 0])?;
+    file.write_all(&id)?;
+    file.write_all(&length.to_le_bytes())?;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut remaining = length;
+    while remaining > 0 {
+        let size = remaining.min(CHUNK_SIZE as u64) as usize;
+        rng.fill_bytes(&mut chunk[..size]);
+        file.write_all(&chunk[..size])?;
+        remaining -= size as u64;
+    }
+    file.sync_all()?;
+    Ok(KeyInfo {
+        id,
+        length,
+        used: false,
+    })
+}
+
+fn read_key_info_from(reader: &mut impl Read) -> Result<KeyInfo, OtpError> {
+    let mut header = [0; KEY_HEADER_SIZE];
+    read_exact(reader, &mut header, "key file")?;
+    if &header[..4] != KEY_MAGIC {
+        return Err(OtpError::InvalidFormat("not a key file".to_string()));
+    }
+    if header[4] != VERSION {
+        return Err(OtpError::InvalidFormat(format!(
+            "unsupported key file version {}",
+            header[4]
+        )));
+    }
+    Ok(KeyInfo {
+        used: header[5] != 0,
+        id: header[6..22].try_into().unwrap(),
+        length: u64::from_le_bytes(header[22..30].try_into().unwrap()),
+    })
+}
+
+pub fn read_key_info(path: impl AsRef<Path>) -> Result<KeyInfo, OtpError> {
+    read_key_info_from(&mut File::open(path)?)
+}
+
+// Reads key bytes, failing if the key is shorter than announced in its header
+struct KeyStream {
+    reader: BufReader<File>,
+    info: KeyInfo,
+    consumed: u64,
+}
+
+impl KeyStream {
+    fn open(path: &Path) -> Result<Self, OtpError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let info = read_key_info_from(&mut reader)?;
+        Ok(KeyStream {
+            reader,
+            info,
+            consumed: 0,
+        })
+    }
+    fn xor(&mut self, data: &mut [u8]) -> Result<(), OtpError> {
+        if self.consumed + data.len() as u64 > self.info.length {
+            return Err(OtpError::KeyTooShort {
+                available: self.info.length,
+            });
+        }
+        let mut key = vec![0; data.len()];
+        read_exact(&mut self.reader, &mut key, "key file")?;
+        self.consumed += data.len() as u64;
+        for (byte, key_byte) in data.iter_mut().zip(key) {
+            *byte ^= key_byte;
+        }
+        Ok(())
+    }
+}
+
+// 64 bit FNV-1a hash, updated chunk by chunk
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Self {
+        Checksum(0xcbf2_9ce4_8422_2325)
+    }
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+fn read_chunk(input: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match input.read(buffer) {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
+
+// Encrypt input with the key in key_path, which is marked as used before any data is written,
+// so a key is burnt even if encryption fails (e.g. because it is too short).
+// Returns the number of bytes encrypted.
+pub fn encrypt(
+    key_path: impl AsRef<Path>,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<u64, OtpError> {
+    let key_path = key_path.as_ref();
+    let mut key = KeyStream::open(key_path)?;
+    if key.info.used {
+        return Err(OtpError::KeyAlreadyUsed);
+    }
+    let mut key_file = OpenOptions::new().write(true).open(key_path)?;
+    key_file.seek(SeekFrom::Start(USED_FLAG_OFFSET))?;
+    key_file.write_all(&[1])?;
+    key_file.sync_all()?;
+
+    output.write_all(CIPHER_MAGIC)?;
+    output.write_all(&[VERSION])?;
+    output.write_all(&key.info.id)?;
+    let mut checksum = Checksum::new();
+    let mut length: u64 = 0;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = read_chunk(input, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &mut buffer[..read];
+        checksum.update(chunk);
+        key.xor(chunk)?;
+        output.write_all(chunk)?;
+        length += read as u64;
+    }
+    let mut trailer = [0; TRAILER_SIZE];
+    trailer[..8].copy_from_slice(&length.to_le_bytes());
+    trailer[8..].copy_from_slice(&checksum.0.to_le_bytes());
+    key.xor(&mut trailer)?;
+    output.write_all(&trailer)?;
+    output.flush()?;
+    Ok(length)
+}
+
+// Decrypt input with the key in key_path, returning the number of bytes decrypted.
+// The data is written while it is decrypted, so after an IntegrityCheckFailed error
+// output has received damaged data and should be discarded.
+pub fn decrypt(
+    key_path: impl AsRef<Path>,
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+) -> Result<u64, OtpError> {
+    let mut key = KeyStream::open(key_path.as_ref())?;
+    let mut header = [0; CIPHER_HEADER_SIZE];
+    read_exact(input, &mut header, "encrypted data")?;
+    if &header[..4] != CIPHER_MAGIC || header[4] != VERSION {
+        return Err(OtpError::InvalidFormat(
+            "not data encrypted with a one-time pad".to_string(),
+        ));
+    }
+    if header[5..] != key.info.id {
+        return Err(OtpError::WrongKey);
+    }
+    let mut checksum = Checksum::new();
+    let mut length: u64 = 0;
+    // the last TRAILER_SIZE bytes read may be the trailer, so they are held back
+    let mut pending: Vec<u8> = Vec::with_capacity(CHUNK_SIZE + TRAILER_SIZE);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = read_chunk(input, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buffer[..read]);
+        if pending.len() > TRAILER_SIZE {
+            let data_length = pending.len() - TRAILER_SIZE;
+            let data = &mut pending[..data_length];
+            key.xor(data)?;
+            checksum.update(data);
+            output.write_all(data)?;
+            length += data_length as u64;
+            pending.drain(..data_length);
+        }
+    }
+    if pending.len() < TRAILER_SIZE {
+        return Err(OtpError::InvalidFormat(
+            "encrypted data is truncated".to_string(),
+        ));
+    }
+    key.xor(&mut pending)?;
+    output.flush()?;
+    let stored_length = u64::from_le_bytes(pending[..8].try_into().unwrap());
+    let stored_checksum = u64::from_le_bytes(pending[8..].try_into().unwrap());
+    if stored_length != length || stored_checksum != checksum.0 {
+        return Err(OtpError::IntegrityCheckFailed);
+    }
+    Ok(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Key file in the temporary directory, deleted when dropped
+    struct TempKey(PathBuf);
+
+    impl TempKey {
+        fn new(name: &str, length: u64) -> Self {
+            let path = std::env::temp_dir().join(format!("otp-{}-{name}.key", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            generate_key_file(&path, length).unwrap();
+            TempKey(path)
+        }
+    }
+
+    impl Drop for TempKey {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn round_trips_binary_data_and_burns_keys() {
+        let key = TempKey::new("round-trip", 200_000);
+        let data: Vec<u8> = (0..150_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let mut encrypted = Vec::new();
+        assert_eq!(
+            encrypt(&key.0, &mut &data[..], &mut encrypted).unwrap(),
+            150_000
+        );
+        assert_eq!(
+            encrypted.len(),
+            CIPHER_HEADER_SIZE + data.len() + TRAILER_SIZE
+        );
+        assert!(read_key_info(&key.0).unwrap().used);
+        let mut decrypted = Vec::new();
+        decrypt(&key.0, &mut &encrypted[..], &mut decrypted).unwrap();
+        assert_eq!(decrypted, data);
+
+        assert!(matches!(
+            encrypt(&key.0, &mut &data[..], &mut Vec::new()),
+            Err(OtpError::KeyAlreadyUsed)
+        ));
+        assert!(generate_key_file(&key.0, 10).is_err());
+    }
+
+    #[test]
+    fn detects_damage_wrong_keys_and_short_keys() {
+        let key = TempKey::new("damage", 100);
+        let mut encrypted = Vec::new();
+        encrypt(&key.0, &mut &b"attack at dawn"[..], &mut encrypted).unwrap();
+        let mut damaged = encrypted.clone();
+        damaged[CIPHER_HEADER_SIZE] ^= 1;
+        assert!(matches!(
+            decrypt(&key.0, &mut &damaged[..], &mut Vec::new()),
+            Err(OtpError::IntegrityCheckFailed)
+        ));
+        assert!(matches!(
+            decrypt(
+                &key.0,
+                &mut &encrypted[..encrypted.len() - 1],
+                &mut Vec::new()
+            ),
+            Err(OtpError::IntegrityCheckFailed)
+        ));
+
+        let other_key = TempKey::new("other", 100);
+        assert!(matches!(
+            decrypt(&other_key.0, &mut &encrypted[..], &mut Vec::new()),
+            Err(OtpError::WrongKey)
+        ));
+        assert!(matches!(
+            encrypt(&other_key.0, &mut &[0; 90][..], &mut Vec::new()),
+            Err(OtpError::KeyTooShort { available: 100 })
+        ));
+    }
+}
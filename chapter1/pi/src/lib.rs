@@ -0,0 +1,348 @@
+// pi/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Several ways to calculate pi to a requested number of decimal places:
+// - Leibniz (as in calculating_pi) and Nilakantha series and a Monte Carlo estimate
+//   in f64, so at most F64_DIGITS decimal places
+// - Machin's formula and the Chudnovsky series in fixed-point big integer arithmetic
+//   for any number of decimal places
+// Each calculation results in a PiReport stating how many decimal places are correct.
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Decimal places an f64 calculation can get right
+pub const F64_DIGITS: usize = 15;
+// Extra decimal places calculated to absorb rounding errors of fixed-point arithmetic
+const GUARD_DIGITS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Leibniz,
+    Nilakantha,
+    MonteCarlo,
+    Machin,
+    Chudnovsky,
+}
+
+impl Method {
+    pub const ALL: [Method; 5] = [
+        Method::Leibniz,
+        Method::Nilakantha,
+        Method::MonteCarlo,
+        Method::Machin,
+        Method::Chudnovsky,
+    ];
+    pub fn is_arbitrary_precision(self) -> bool {
+        matches!(self, Method::Machin | Method::Chudnovsky)
+    }
+}
+
+pub struct PiOptions {
+    // Series terms or random points after which the f64 methods give up
+    pub max_terms: u64,
+    // Seed of the Monte Carlo estimate, so that results can be reproduced
+    pub seed: u64,
+}
+
+impl Default for PiOptions {
+    fn default() -> Self {
+        PiOptions {
+            max_terms: 10_000_000,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PiReport {
+    pub method: Method,
+    pub target_digits: usize,
+    // "3." followed by the decimal places calculated (truncated, not rounded)
+    pub value: String,
+    // Number of leading decimal places that agree with pi
+    pub correct_digits: usize,
+    // Series terms, random points or (for Chudnovsky) series terms summed
+    pub terms: u64,
+    // The method's own bound (Monte Carlo: standard error) of the absolute error
+    pub error_bound: f64,
+    pub elapsed: Duration,
+}
+
+impl PiReport {
+    pub fn reached_target(&self) -> bool {
+        self.correct_digits >= self.target_digits
+    }
+}
+
+impl fmt::Display for PiReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{:<11} {:>6} terms, {:>4} of {} digits correct, error bound {:.1e}, {:?}: {}",
+            format!("{:?}", self.method),
+            self.terms,
+            self.correct_digits,
+            self.target_digits,
+            self.error_bound,
+            self.elapsed,
+            self.value
+        )
+    }
+}
+
+// Alternating series: the error is less than the first term left out
+fn leibniz(tolerance: f64, max_terms: u64) -> (f64, u64, f64) {
+    let mut pi = 0.0;
+    let mut sign = 1.0;
+    let mut terms = 0;
+    loop {
+        let term = 4.0 / (2 * terms + 1) as f64;
+        if term < tolerance || terms == max_terms {
+            return (pi, terms, term);
+        }
+        pi += sign * term;
+        sign = -sign;
+        terms += 1;
+    }
+}
+
+// pi = 3 + 4/(2*3*4) - 4/(4*5*6) + 4/(6*7*8) - ...
+fn nilakantha(tolerance: f64, max_terms: u64) -> (f64, u64, f64) {
+    let mut pi = 3.0;
+    let mut sign = 1.0;
+    let mut terms = 0;
+    loop {
+        let k = 2.0 * (terms + 1) as f64;
+        let term = 4.0 / (k * (k + 1.0) * (k + 2.0));
+        if term < tolerance || terms == max_terms {
+            return (pi, terms, term);
+        }
+        pi += sign * term;
+        sign = -sign;
+        terms += 1;
+    }
+}
+
+// Fraction of random points in the unit square inside the quarter circle, times 4
+fn monte_carlo(tolerance: f64, max_terms: u64, seed: u64) -> (f64, u64, f64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut inside = 0u64;
+    let mut points = 0u64;
+    let estimate = |inside: u64, points: u64| {
+        let fraction = inside as f64 / points as f64;
+        let standard_error = 4.0 * (fraction * (1.0 - fraction) / points as f64).sqrt();
+        (4.0 * fraction, standard_error)
+    };
+    while points < max_terms {
+        let (x, y): (f64, f64) = (rng.random(), rng.random());
+        if x * x + y * y <= 1.0 {
+            inside += 1;
+        }
+        points += 1;
+        // checking now and then is enough; the error shrinks with the square root of points
+        if points.is_multiple_of(4096) && estimate(inside, points).1 < tolerance {
+            break;
+        }
+    }
+    let (pi, standard_error) = estimate(inside, points.max(1));
+    (pi, points, standard_error)
+}
+
+// arctan(1/x) * scale = scale/x - scale/(3x^3) + scale/(5x^5) - ...
+fn arctan_inverse(x: u32, scale: &BigInt) -> (BigInt, u64) {
+    let x_squared = BigInt::from(x) * x;
+    let mut power = scale / x; // scale / x^(2k+1)
+    let mut sum = power.clone();
+    let mut terms = 1;
+    let mut k = 1u64;
+    while !power.is_zero() {
+        power /= &x_squared;
+        let term = &power / (2 * k + 1);
+        if k % 2 == 1 {
+            sum -= term;
+        } else {
+            sum += term;
+        }
+        k += 1;
+        terms += 1;
+    }
+    (sum, terms)
+}
+
+// pi = 16 arctan(1/5) - 4 arctan(1/239), returning pi * 10^places
+fn machin(places: usize) -> (BigInt, u64) {
+    let scale = BigInt::from(10).pow(places as u32);
+    let (a, terms_a) = arctan_inverse(5, &scale);
+    let (b, terms_b) = arctan_inverse(239, &scale);
+    (a * 16 - b * 4, terms_a + terms_b)
+}
+
+// Binary splitting of the Chudnovsky series: P, Q and T of the terms a..b
+fn chudnovsky_split(a: u64, b: u64) -> (BigInt, BigInt, BigInt) {
+    if b - a == 1 {
+        let (p, q) = if a == 0 {
+            (BigInt::one(), BigInt::one())
+        } else {
+            let a_big = BigInt::from(a);
+            (
+                BigInt::from(6 * a - 5) * (2 * a - 1) * (6 * a - 1),
+                &a_big * &a_big * &a_big * 10_939_058_860_032_000u64,
+            )
+        };
+        let mut t = &p * (BigInt::from(545_140_134u64) * a + 13_591_409u64);
+        if a % 2 == 1 {
+            t = -t;
+        }
+        return (p, q, t);
+    }
+    let middle = (a + b) / 2;
+    let (p1, q1, t1) = chudnovsky_split(a, middle);
+    let (p2, q2, t2) = chudnovsky_split(middle, b);
+    (&p1 * p2, &q1 * &q2, t1 * q2 + p1 * t2)
+}
+
+// pi = 426880 sqrt(10005) Q / T; every term adds about 14 decimal places
+fn chudnovsky(places: usize) -> (BigInt, u64) {
+    let terms = places as u64 / 14 + 2;
+    let (_, q, t) = chudnovsky_split(0, terms);
+    let scale = BigUint::from(10u32).pow(places as u32);
+    let sqrt_10005 = BigInt::from((BigUint::from(10_005u32) * &scale * &scale).sqrt());
+    (BigInt::from(426_880) * sqrt_10005 * q / t, terms)
+}
+
+// "3." followed by the first places decimal places of value / 10^scale_places
+fn to_decimal(value: &BigInt, scale_places: usize, places: usize) -> String {
+    let digits = value.to_string();
+    let integer_length = digits.len() - scale_places;
+    let mut text = format!(
+        "{}.{}",
+        &digits[..integer_length],
+        &digits[integer_length..]
+    );
+    text.truncate(integer_length + 1 + places);
+    text
+}
+
+fn f64_to_decimal(value: f64, places: usize) -> String {
+    let mut text = format!("{value:.width$}", width = places.min(F64_DIGITS) + 3);
+    text.truncate(2 + places.min(F64_DIGITS));
+    text
+}
+
+// Pi to places decimal places (truncated), calculated with the Chudnovsky series
+pub fn pi_digits(places: usize) -> String {
+    let (value, _) = chudnovsky(places + GUARD_DIGITS);
+    to_decimal(&value, places + GUARD_DIGITS, places)
+}
+
+fn count_correct_digits(value: &str, reference: &str) -> usize {
+    value
+        .bytes()
+        .zip(reference.bytes())
+        .skip(2)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+pub fn calculate_pi(method: Method, digits: usize, options: &PiOptions) -> PiReport {
+    let start = Instant::now();
+    let tolerance = 0.5 * 10f64.powi(-(digits.min(F64_DIGITS) as i32));
+    let (value, terms, error_bound) = match method {
+        Method::Leibniz | Method::Nilakantha | Method::MonteCarlo => {
+            let (pi, terms, error_bound) = match method {
+                Method::Leibniz => leibniz(tolerance, options.max_terms),
+                Method::Nilakantha => nilakantha(tolerance, options.max_terms),
+                _ => monte_carlo(tolerance, options.max_terms, options.seed),
+            };
+            (f64_to_decimal(pi, digits), terms, error_bound)
+        }
+        Method::Machin | Method::Chudnovsky => {
+            let places = digits + GUARD_DIGITS;
+            let (pi, terms) = if method == Method::Machin {
+                machin(places)
+            } else {
+                chudnovsky(places)
+            };
+            let error_bound = 10f64.powi(-(digits.min(300) as i32));
+            (to_decimal(&pi, places, digits), terms, error_bound)
+        }
+    };
+    let elapsed = start.elapsed();
+    // the reference comes from the other arbitrary precision method
+    let reference = if method == Method::Chudnovsky {
+        let (pi, _) = machin(digits + GUARD_DIGITS);
+        to_decimal(&pi, digits + GUARD_DIGITS, digits)
+    } else {
+        pi_digits(digits)
+    };
+    PiReport {
+        method,
+        target_digits: digits,
+        correct_digits: count_correct_digits(&value, &reference),
+        value,
+        terms,
+        error_bound,
+        elapsed,
+    }
+}
+
+pub fn compare_methods(digits: usize, options: &PiOptions) -> Vec<PiReport> {
+    Method::ALL
+        .iter()
+        .map(|&method| calculate_pi(method, digits, options))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PI_100: &str = "3.1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+
+    #[test]
+    fn arbitrary_precision_methods_are_exact() {
+        assert_eq!(pi_digits(100), PI_100);
+        for method in [Method::Machin, Method::Chudnovsky] {
+            let report = calculate_pi(method, 100, &PiOptions::default());
+            assert_eq!(report.value, PI_100);
+            assert_eq!(report.correct_digits, 100);
+            assert!(report.reached_target());
+        }
+        let report = calculate_pi(Method::Chudnovsky, 1000, &PiOptions::default());
+        assert!(report.reached_target());
+        assert_eq!(report.terms, 1000 / 14 + 2 + 1);
+    }
+
+    #[test]
+    fn f64_methods_converge_to_their_target() {
+        let options = PiOptions::default();
+        let report = calculate_pi(Method::Nilakantha, 8, &options);
+        assert!(report.reached_target(), "{report}");
+        let report = calculate_pi(Method::Leibniz, 5, &options);
+        assert!(report.reached_target(), "{report}");
+        assert!(report.terms > 100_000);
+        let report = calculate_pi(Method::Leibniz, 12, &options);
+        assert!(!report.reached_target());
+        assert_eq!(report.terms, options.max_terms);
+        let report = calculate_pi(Method::MonteCarlo, 2, &options);
+        assert!(report.correct_digits >= 1, "{report}");
+        assert!(report.error_bound < 0.01);
+    }
+}
@@ -0,0 +1,428 @@
+// secret_sharing/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Splitting a secret into shares, generalising the two "keys" of unbreakable_encryption:
+// - XOR n-of-n: n - 1 random shares and the secret XOR all of them; all n are needed
+// - Shamir k-of-n: every byte of the secret is the constant term of a random polynomial
+//   of degree k - 1 over GF(256); share i holds the polynomials' values at x = i,
+//   so any k shares determine the polynomials and fewer reveal nothing.
+// Serialised share: "SHR", version, scheme (0 = XOR, 1 = Shamir), set id (u64),
+//   threshold, total, index, data length (u32), data (integers little-endian)
+use rand::RngCore;
+use std::fmt;
+use std::str::FromStr;
+
+const MAGIC: &[u8; 3] = b"SHR";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 3 + 1 + 1 + 8 + 3 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Xor,
+    Shamir,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    pub scheme: Scheme,
+    // Random number identifying the shares created by one split
+    pub set_id: u64,
+    // Number of shares needed to reconstruct the secret (total for XOR)
+    pub threshold: u8,
+    pub total: u8,
+    // 1..=total; the x coordinate for Shamir's scheme
+    pub index: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharingError {
+    InvalidParameters(String),
+    TooFewShares { needed: u8, given: usize },
+    // Shares from different splits, duplicated shares, or shares contradicting each other
+    InconsistentShares(String),
+    InvalidFormat(String),
+}
+
+impl fmt::Display for SharingError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharingError::InvalidParameters(message) => {
+                write!(formatter, "invalid parameters: {message}")
+            }
+            SharingError::TooFewShares { needed, given } => write!(
+                formatter,
+                "{needed} shares are needed to reconstruct the secret, but only {given} given"
+            ),
+            SharingError::InconsistentShares(message) => {
+                write!(formatter, "inconsistent shares: {message}")
+            }
+            SharingError::InvalidFormat(message) => write!(formatter, "invalid share: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for SharingError {}
+
+// Arithmetic in GF(256) with the AES polynomial x^8 + x^4 + x^3 + x + 1:
+// addition is XOR, multiplication is polynomial multiplication modulo that polynomial
+fn gf_multiply(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 = a^-1, as the multiplicative group has 255 elements
+fn gf_inverse(a: u8) -> u8 {
+    let mut result = 1;
+    for _ in 0..254 {
+        result = gf_multiply(result, a);
+    }
+    result
+}
+
+// Horner's method; coefficients[0] is the constant term
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |value, coefficient| gf_multiply(value, x) ^ coefficient)
+}
+
+// Value at x of the polynomial through the points (xs[i], ys[i]) (Lagrange interpolation)
+fn interpolate(xs: &[u8], ys: &[u8], x: u8) -> u8 {
+    let mut value = 0;
+    for (i, (&xi, &yi)) in xs.iter().zip(ys).enumerate() {
+        let mut numerator = 1;
+        let mut denominator = 1;
+        for (j, &xj) in xs.iter().enumerate() {
+            if i != j {
+                numerator = gf_multiply(numerator, x ^ xj);
+                denominator = gf_multiply(denominator, xi ^ xj);
+            }
+        }
+        value ^= gf_multiply(yi, gf_multiply(numerator, gf_inverse(denominator)));
+    }
+    value
+}
+
+fn new_set_id() -> u64 {
+    rand::rng().next_u64()
+}
+
+pub fn split_xor(secret: &[u8], total: u8) -> Result<Vec<Share>, SharingError> {
+    if total < 2 {
+        return Err(SharingError::InvalidParameters(
+            "at least 2 shares are needed".to_string(),
+        ));
+    }
+    let set_id = new_set_id();
+    let mut last = secret.to_vec();
+    let mut shares = Vec::with_capacity(total as usize);
+    for index in 1..total {
+        let mut data = vec![0; secret.len()];
+        rand::rng().fill_bytes(&mut data);
+        for (byte, random) in last.iter_mut().zip(&data) {
+            *byte ^= random;
+        }
+        shares.push(Share {
+            scheme: Scheme::Xor,
+            set_id,
+            threshold: total,
+            total,
+            index,
+            data,
+        });
+    }
+    shares.push(Share {
+        scheme: Scheme::Xor,
+        set_id,
+        threshold: total,
+        total,
+        index: total,
+        data: last,
+    });
+    Ok(shares)
+}
+
+pub fn split_shamir(secret: &[u8], threshold: u8, total: u8) -> Result<Vec<Share>, SharingError> {
+    if threshold == 0 || threshold > total {
+        return Err(SharingError::InvalidParameters(format!(
+            "threshold {threshold} must be between 1 and the number of shares {total}"
+        )));
+    }
+    let set_id = new_set_id();
+    let mut shares: Vec<Share> = (1..=total)
+        .map(|index| Share {
+            scheme: Scheme::Shamir,
+            set_id,
+            threshold,
+            total,
+            index,
+            data: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+    let mut coefficients = vec![0; threshold as usize];
+    for &byte in secret {
+        coefficients[0] = byte;
+        rand::rng().fill_bytes(&mut coefficients[1..]);
+        for share in shares.iter_mut() {
+            share.data.push(evaluate(&coefficients, share.index));
+        }
+    }
+    Ok(shares)
+}
+
+fn check_consistency(shares: &[Share]) -> Result<(), SharingError> {
+    let Some(first) = shares.first() else {
+        return Ok(());
+    };
+    let inconsistent = |message: &str| Err(SharingError::InconsistentShares(message.to_string()));
+    let mut seen = [false; 256];
+    for share in shares {
+        if share.scheme != first.scheme
+            || share.set_id != first.set_id
+            || share.threshold != first.threshold
+            || share.total != first.total
+        {
+            return inconsistent("shares belong to different splits");
+        }
+        if share.data.len() != first.data.len() {
+            return inconsistent("shares have different lengths");
+        }
+        if share.index == 0 || share.index > share.total {
+            return inconsistent("share index out of range");
+        }
+        if std::mem::replace(&mut seen[share.index as usize], true) {
+            return Err(SharingError::InconsistentShares(format!(
+                "share {} given twice",
+                share.index
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Reconstruct the secret. For Shamir's scheme, shares beyond the threshold are used
+// to check that all shares lie on the same polynomials.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>, SharingError> {
+    check_consistency(shares)?;
+    let Some(first) = shares.first() else {
+        return Err(SharingError::TooFewShares {
+            needed: 1,
+            given: 0,
+        });
+    };
+    if shares.len() < first.threshold as usize {
+        return Err(SharingError::TooFewShares {
+            needed: first.threshold,
+            given: shares.len(),
+        });
+    }
+    match first.scheme {
+        Scheme::Xor => Ok(shares
+            .iter()
+            .fold(vec![0; first.data.len()], |mut secret, share| {
+                for (byte, share_byte) in secret.iter_mut().zip(&share.data) {
+                    *byte ^= share_byte;
+                }
+                secret
+            })),
+        Scheme::Shamir => {
+            let (used, extra) = shares.split_at(first.threshold as usize);
+            let xs: Vec<u8> = used.iter().map(|share| share.index).collect();
+            let mut secret = Vec::with_capacity(first.data.len());
+            let mut ys = vec![0; xs.len()];
+            for position in 0..first.data.len() {
+                for (y, share) in ys.iter_mut().zip(used) {
+                    *y = share.data[position];
+                }
+                secret.push(interpolate(&xs, &ys, 0));
+                if extra
+                    .iter()
+                    .any(|share| interpolate(&xs, &ys, share.index) != share.data[position])
+                {
+                    return Err(SharingError::InconsistentShares(
+                        "shares do not lie on a common polynomial".to_string(),
+                    ));
+                }
+            }
+            Ok(secret)
+        }
+    }
+}
+
+impl Share {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.scheme {
+            Scheme::Xor => 0,
+            Scheme::Shamir => 1,
+        });
+        bytes.extend_from_slice(&self.set_id.to_le_bytes());
+        bytes.extend_from_slice(&[self.threshold, self.total, self.index]);
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Share, SharingError> {
+        let invalid = |message: &str| Err(SharingError::InvalidFormat(message.to_string()));
+        if bytes.len() < HEADER_SIZE {
+            return invalid("too short");
+        }
+        if &bytes[..3] != MAGIC {
+            return invalid("not a share");
+        }
+        if bytes[3] != VERSION {
+            return invalid("unsupported version");
+        }
+        let scheme = match bytes[4] {
+            0 => Scheme::Xor,
+            1 => Scheme::Shamir,
+            _ => return invalid("unknown scheme"),
+        };
+        let length = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as usize;
+        if bytes.len() != HEADER_SIZE + length {
+            return invalid("data length does not match");
+        }
+        Ok(Share {
+            scheme,
+            set_id: u64::from_le_bytes(bytes[5..13].try_into().unwrap()),
+            threshold: bytes[13],
+            total: bytes[14],
+            index: bytes[15],
+            data: bytes[HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+// Text form of a share: its serialised bytes in hexadecimal
+impl fmt::Display for Share {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.to_bytes() {
+            write!(formatter, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Share {
+    type Err = SharingError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if !text.len().is_multiple_of(2) || !text.is_ascii() {
+            return Err(SharingError::InvalidFormat(
+                "not a hexadecimal string".to_string(),
+            ));
+        }
+        let bytes = (0..text.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&text[index..index + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| SharingError::InvalidFormat("not a hexadecimal string".to_string()))?;
+        Share::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"One Time Pad!\x00\xff";
+
+    #[test]
+    fn gf256_arithmetic() {
+        assert_eq!(gf_multiply(0x57, 0x83), 0xc1);
+        for a in 1..=255 {
+            assert_eq!(gf_multiply(a, gf_inverse(a)), 1);
+        }
+    }
+
+    #[test]
+    fn xor_shares_need_all_shares() {
+        let shares = split_xor(SECRET, 4).unwrap();
+        assert_eq!(combine(&shares).unwrap(), SECRET);
+        assert_eq!(
+            combine(&shares[1..]),
+            Err(SharingError::TooFewShares {
+                needed: 4,
+                given: 3
+            })
+        );
+        assert!(split_xor(SECRET, 1).is_err());
+    }
+
+    #[test]
+    fn any_threshold_shamir_shares_reconstruct() {
+        let shares = split_shamir(SECRET, 3, 5).unwrap();
+        for (a, b, c) in [(0, 1, 2), (4, 2, 0), (1, 3, 4)] {
+            let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+            assert_eq!(combine(&subset).unwrap(), SECRET);
+        }
+        assert_eq!(combine(&shares).unwrap(), SECRET);
+        assert!(matches!(
+            combine(&shares[..2]),
+            Err(SharingError::TooFewShares { needed: 3, .. })
+        ));
+        assert!(split_shamir(SECRET, 6, 5).is_err());
+    }
+
+    #[test]
+    fn rejects_inconsistent_shares() {
+        let shares = split_shamir(SECRET, 2, 3).unwrap();
+        let mut tampered = shares.clone();
+        tampered[2].data[0] ^= 1;
+        assert!(matches!(
+            combine(&tampered),
+            Err(SharingError::InconsistentShares(_))
+        ));
+        let duplicated = [shares[0].clone(), shares[0].clone()];
+        assert!(matches!(
+            combine(&duplicated),
+            Err(SharingError::InconsistentShares(_))
+        ));
+        let other = split_shamir(SECRET, 2, 3).unwrap();
+        let mixed = [shares[0].clone(), other[1].clone()];
+        assert!(matches!(
+            combine(&mixed),
+            Err(SharingError::InconsistentShares(_))
+        ));
+    }
+
+    #[test]
+    fn shares_serialise() {
+        for share in split_shamir(SECRET, 2, 3).unwrap() {
+            assert_eq!(Share::from_bytes(&share.to_bytes()).unwrap(), share);
+            assert_eq!(share.to_string().parse::<Share>().unwrap(), share);
+        }
+        let bytes = split_xor(SECRET, 2).unwrap()[0].to_bytes();
+        assert!(Share::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!("xyz".parse::<Share>().is_err());
+    }
+}
@@ -14,6 +14,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use pi::{compare_methods, PiOptions};
 // use std::time::Instant; // remove comment to measure runtime
 
 fn calculate_pi(n_terms: usize) -> f64 {
@@ -34,5 +35,9 @@ fn main() {
     // let pi = calculate_pi(1000000);                    // remove comment to measure runtime
     // let duration = start.elapsed().as_millis();        // remove comment to measure runtime
     // println!("Calculating {} took {}ms.",pi,duration); // remove comment to measure runtime
-    println!("{}", calculate_pi(10000000))
+    println!("{}", calculate_pi(10000000));
+    // the library's methods, aiming for 12 correct decimal places
+    for report in compare_methods(12, &PiOptions::default()) {
+        println!("{report}");
+    }
 }
@@ -0,0 +1,98 @@
+// compress.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Usage:
+//   compress <file>                             compare all codecs on a file
+//   compress <codec> <input file> <output file> compress a file
+//   compress -d <codec> <input file> <output file> decompress a file
+// Codecs are huffman, lz77 and rle (see src/codec.rs)
+use codec::{all_codecs, codec_by_name, Codec};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+
+fn ratio(original: u64, compressed: u64) -> f64 {
+    if original == 0 {
+        100.0
+    } else {
+        100.0 * compressed as f64 / original as f64
+    }
+}
+
+fn compare_all(path: &str) -> io::Result<()> {
+    let data = fs::read(path)?;
+    for codec in all_codecs() {
+        let encoded = codec.encode_bytes(&data)?;
+        let round_trip = codec.decode_bytes(&encoded)? == data;
+        println!(
+            "{:8} {} -> {} bytes ({:.1}%), round trip {}",
+            codec.name(),
+            data.len(),
+            encoded.len(),
+            ratio(data.len() as u64, encoded.len() as u64),
+            if round_trip { "ok" } else { "FAILED" }
+        );
+    }
+    Ok(())
+}
+
+fn convert(codec: &dyn Codec, decompress: bool, input: &str, output: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    if decompress {
+        codec.decode(&mut reader, &mut writer)?;
+    } else {
+        codec.encode(&mut reader, &mut writer)?;
+    }
+    drop(writer);
+    let input_size = fs::metadata(input)?.len();
+    let output_size = fs::metadata(output)?.len();
+    println!(
+        "{input}: {input_size} bytes -> {output}: {output_size} bytes ({:.1}%)",
+        if decompress {
+            ratio(output_size, input_size)
+        } else {
+            ratio(input_size, output_size)
+        }
+    );
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (decompress, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-d" => (true, rest),
+        _ => (false, &args[..]),
+    };
+    let result = match args {
+        [path] if !decompress => compare_all(path),
+        [name, input, output] => match codec_by_name(name) {
+            Some(codec) => convert(codec.as_ref(), decompress, input, output),
+            None => {
+                println!("Unknown codec {name}, use huffman, lz77 or rle");
+                return;
+            }
+        },
+        _ => {
+            println!("Usage: compress [-d] <codec> <input file> <output file>");
+            println!("   or: compress <file> to compare all codecs");
+            return;
+        }
+    };
+    if let Err(error) = result {
+        println!("Error: {error}");
+    }
+}
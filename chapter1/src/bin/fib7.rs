@@ -0,0 +1,39 @@
+// fib7.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// fib1 to fib6 overflow usize after fib(93); the library versions use big integers
+use fibonacci::{
+    fib_fast_doubling, fib_matrix, fib_mod, FibonacciGenerator, LinearRecurrence,
+};
+
+fn main() {
+    println!("{}", fib_fast_doubling(5));
+    println!("{}", fib_fast_doubling(50));
+    println!("{}", fib_matrix(100));
+    println!(
+        "fib(10000) has {} digits",
+        fib_fast_doubling(10_000).to_string().len()
+    );
+    println!(
+        "The last 9 digits of fib(10^18) are {:09}",
+        fib_mod(1_000_000_000_000_000_000, 1_000_000_000)
+    );
+    for fib in FibonacciGenerator::new().skip(95).take(3) {
+        println!("{fib}");
+    }
+    let tribonacci = LinearRecurrence::from_i64(&[1, 1, 1], &[0, 0, 1]);
+    println!("tribonacci(200) = {}", tribonacci.nth(200));
+}
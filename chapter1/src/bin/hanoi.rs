@@ -14,6 +14,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use hanoi::{move_count, verify, write_json, Moves};
 use std::fmt;
 
 struct Stack<T> {
@@ -79,4 +80,17 @@ fn main() {
     println!("Tower A: {}", tower_a);
     println!("Tower B: {}", tower_b);
     println!("Tower C: {}", tower_c);
+
+    // the library streams the moves, also for more than 3 pegs
+    for next in Moves::new(tower_height as usize, 3) {
+        println!("{next}");
+    }
+    for pegs in 3..=6 {
+        println!("20 discs, {pegs} pegs: {} moves", move_count(20, pegs));
+    }
+    match verify(20, 4, Moves::new(20, 4)) {
+        Ok(moves) => println!("Verified {moves} moves with 4 pegs"),
+        Err(error) => println!("Error: {error}"),
+    }
+    write_json(4, 4, Moves::new(4, 4), &mut std::io::stdout()).expect("Cannot write to stdout!?");
 }
@@ -0,0 +1,79 @@
+// otp.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Usage:
+//   otp keygen <key file> <length in bytes>
+//   otp info <key file>
+//   otp encrypt <key file> <input file> <output file>
+//   otp decrypt <key file> <input file> <output file>
+// The file formats are described in src/otp.rs
+use otp::{decrypt, encrypt, generate_key_file, read_key_info, KeyInfo, OtpError};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+
+fn print_info(info: &KeyInfo) {
+    let id: String = info.id.iter().map(|byte| format!("{byte:02x}")).collect();
+    println!(
+        "key {id}: {} bytes, {}",
+        info.length,
+        if info.used { "used" } else { "unused" }
+    );
+}
+
+fn convert(command: &str, key: &str, input: &str, output: &str) -> Result<u64, OtpError> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let result = if command == "encrypt" {
+        encrypt(key, &mut reader, &mut writer)
+    } else {
+        decrypt(key, &mut reader, &mut writer)
+    };
+    drop(writer);
+    if result.is_err() {
+        // never leave partially encrypted or damaged data behind
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["keygen", key, length] => match length.parse() {
+            Ok(length) => generate_key_file(key, length).map(|info| print_info(&info)),
+            Err(_) => {
+                println!("Invalid key length {length}");
+                return;
+            }
+        },
+        ["info", key] => read_key_info(key).map(|info| print_info(&info)),
+        [command @ ("encrypt" | "decrypt"), key, input, output] => {
+            convert(command, key, input, output)
+                .map(|length| println!("{command}ed {length} bytes into {output}"))
+        }
+        _ => {
+            println!("Usage: otp keygen <key file> <length in bytes>");
+            println!("       otp info <key file>");
+            println!("       otp encrypt|decrypt <key file> <input file> <output file>");
+            return;
+        }
+    };
+    if let Err(error) = result {
+        println!("Error: {error}");
+    }
+}
@@ -0,0 +1,42 @@
+// secret_sharing.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 1
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use secret_sharing::{combine, split_shamir, split_xor};
+
+fn main() {
+    let secret = "One Time Pad!";
+    // unbreakable_encryption's two keys are a XOR 2-of-2 split
+    let shares = split_xor(secret.as_bytes(), 2).unwrap();
+    println!(
+        "XOR 2-of-2: {}",
+        String::from_utf8_lossy(&combine(&shares).unwrap())
+    );
+
+    let shares = split_shamir(secret.as_bytes(), 3, 5).unwrap();
+    for share in &shares {
+        println!("share {}: {share}", share.index);
+    }
+    let chosen = [shares[4].clone(), shares[0].clone(), shares[2].clone()];
+    match combine(&chosen) {
+        Ok(secret) => println!(
+            "Shamir 3-of-5 with shares 5, 1, 3: {}",
+            String::from_utf8_lossy(&secret)
+        ),
+        Err(error) => println!("Error: {error}"),
+    }
+    if let Err(error) = combine(&shares[..2]) {
+        println!("With shares 1 and 2 only: {error}");
+    }
+}
@@ -15,7 +15,6 @@
 // limitations under the License.
 
 use rand::RngCore;
-use std::str;
 
 fn random_key(length: usize) -> Vec<u8> {
     let mut dummy = vec![0; length];
@@ -38,12 +37,13 @@ fn decrypt(key1: &[u8], key2: &[u8]) -> String {
     for index in 0..key1.len() {
         decrypted.push(key1[index] ^ key2[index]);
     }
-    let temp = str::from_utf8(&decrypted).unwrap();
-    String::from(temp)
+    // Decrypting with the wrong key yields bytes that are not valid UTF-8
+    String::from_utf8_lossy(&decrypted).into_owned()
 }
 
 fn main() {
     let (key1, key2) = encrypt(&String::from("One Time Pad!"));
     let result = decrypt(&key1, &key2);
     println!("{}", result);
+    // For files and binary data with key files see src/bin/otp.rs
 }
@@ -0,0 +1,158 @@
+// fasta.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Reader for FASTA files: each record starts with a header line ">id description"
+// followed by any number of sequence lines. Blank lines and comment lines
+// starting with ';' are ignored.
+use crate::nucleotide::{parse_lines, Base};
+use crate::DnaError;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastaRecord {
+    pub id: String,
+    pub description: String,
+    pub sequence: Vec<Base>,
+}
+
+// Iterator over the records of a FASTA file, reading one record at a time
+pub struct FastaReader<R> {
+    lines: Lines<R>,
+    line_number: usize,
+    // header line of the record being read
+    next_header: Option<String>,
+    failed: bool,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        FastaReader {
+            lines: reader.lines(),
+            line_number: 0,
+            next_header: None,
+            failed: false,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<FastaRecord>, DnaError> {
+        let mut sequence = Vec::new();
+        for line in self.lines.by_ref() {
+            let line = line?;
+            self.line_number += 1;
+            let trimmed = line.trim();
+            if let Some(header) = trimmed.strip_prefix('>') {
+                if let Some(header) = self.next_header.replace(header.to_string()) {
+                    return Ok(Some(record(&header, sequence)));
+                }
+            } else if trimmed.is_empty() || trimmed.starts_with(';') {
+                continue;
+            } else if self.next_header.is_none() {
+                return Err(DnaError::MissingHeader {
+                    line: self.line_number,
+                });
+            } else {
+                sequence.extend(parse_lines(&line, self.line_number, |base, _, _| Ok(base))?);
+            }
+        }
+        Ok(self
+            .next_header
+            .take()
+            .map(|header| record(&header, sequence)))
+    }
+}
+
+fn record(header: &str, sequence: Vec<Base>) -> FastaRecord {
+    let (id, description) = header
+        .split_once(char::is_whitespace)
+        .unwrap_or((header, ""));
+    FastaRecord {
+        id: id.to_string(),
+        description: description.trim().to_string(),
+        sequence,
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<FastaRecord, DnaError>;
+    // Stops after the first error
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_record().transpose();
+        if matches!(result, Some(Err(_))) {
+            self.failed = true;
+        }
+        result
+    }
+}
+
+pub fn read_fasta(reader: impl BufRead) -> Result<Vec<FastaRecord>, DnaError> {
+    FastaReader::new(reader).collect()
+}
+
+pub fn read_fasta_file(path: impl AsRef<Path>) -> Result<Vec<FastaRecord>, DnaError> {
+    read_fasta(BufReader::new(File::open(path)?))
+}
+
+pub fn parse_fasta(text: &str) -> Result<Vec<FastaRecord>, DnaError> {
+    read_fasta(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_multiple_records() {
+        let text =
+            "; a comment\n>seq1 first test sequence\nACGT\nacgn\n\n>seq2\r\nRY ww\r\n>empty\n";
+        let records = parse_fasta(text).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].id, "seq1");
+        assert_eq!(records[0].description, "first test sequence");
+        assert_eq!(records[0].sequence.len(), 8);
+        assert_eq!(records[0].sequence[7], Base::N);
+        assert_eq!(records[1].id, "seq2");
+        assert_eq!(
+            records[1].sequence,
+            vec![Base::R, Base::Y, Base::W, Base::W]
+        );
+        assert!(records[2].sequence.is_empty());
+    }
+
+    #[test]
+    fn reports_errors_with_file_positions() {
+        assert!(matches!(
+            parse_fasta("ACGT\n>seq\nACGT"),
+            Err(DnaError::MissingHeader { line: 1 })
+        ));
+        assert!(matches!(
+            parse_fasta(">seq1\nACGT\n>seq2\nAC\nAXG"),
+            Err(DnaError::InvalidBase {
+                found: 'X',
+                line: 5,
+                column: 2
+            })
+        ));
+        let mut reader = FastaReader::new(">seq1\nACGT\n>seq2\nA!".as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
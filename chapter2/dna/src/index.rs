@@ -0,0 +1,276 @@
+// index.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// A sequence indexed once so that many queries can be answered without scanning it:
+// a hash index of all k-mers (substrings of length k) for k-mer lookups and counts,
+// and a suffix array with LCP array for substrings of any length and repeats.
+// Like the exact search in pattern, codes are compared for equality (N only matches N).
+use crate::nucleotide::Base;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub struct IndexedSequence {
+    bases: Vec<Base>,
+    k: usize,
+    // start positions of every k-mer, in increasing order
+    kmers: HashMap<Vec<Base>, Vec<usize>>,
+    // start positions of all suffixes in lexicographic order
+    suffix_array: Vec<usize>,
+    // lcp[i]: length of the longest common prefix of the suffixes at suffix_array[i - 1] and suffix_array[i]
+    lcp: Vec<usize>,
+}
+
+// Any fixed order of the codes works for a suffix array; this is alphabetical order
+fn rank(base: Base) -> usize {
+    base.to_char() as usize
+}
+
+// Prefix doubling: after the round for length h, suffixes are sorted by their first 2h bases
+fn build_suffix_array(bases: &[Base]) -> Vec<usize> {
+    let n = bases.len();
+    let mut suffix_array: Vec<usize> = (0..n).collect();
+    let mut ranks: Vec<usize> = bases.iter().map(|base| rank(*base)).collect();
+    let mut next_ranks = vec![0; n];
+    if n < 2 {
+        return suffix_array;
+    }
+    let mut h = 1;
+    loop {
+        // suffixes shorter than h + 1 sort before all others with the same first h bases
+        let key = |i: usize| (ranks[i], ranks.get(i + h).map_or(0, |rank| rank + 1));
+        suffix_array.sort_unstable_by_key(|&i| key(i));
+        next_ranks[suffix_array[0]] = 0;
+        for window in 1..n {
+            let (previous, current) = (suffix_array[window - 1], suffix_array[window]);
+            next_ranks[current] = next_ranks[previous] + usize::from(key(previous) != key(current));
+        }
+        std::mem::swap(&mut ranks, &mut next_ranks);
+        if ranks[suffix_array[n - 1]] == n - 1 {
+            break;
+        }
+        h *= 2;
+    }
+    suffix_array
+}
+
+// Kasai's algorithm: the LCP of the suffix starting at i + 1 with its predecessor
+// is at most one less than that of the suffix starting at i, so it runs in linear time
+fn build_lcp(bases: &[Base], suffix_array: &[usize]) -> Vec<usize> {
+    let n = bases.len();
+    let mut position_in_array = vec![0; n];
+    for (index, &start) in suffix_array.iter().enumerate() {
+        position_in_array[start] = index;
+    }
+    let mut lcp = vec![0; n];
+    let mut length = 0;
+    for start in 0..n {
+        let index = position_in_array[start];
+        if index == 0 {
+            length = 0;
+            continue;
+        }
+        let previous = suffix_array[index - 1];
+        while start + length < n
+            && previous + length < n
+            && bases[start + length] == bases[previous + length]
+        {
+            length += 1;
+        }
+        lcp[index] = length;
+        length = length.saturating_sub(1);
+    }
+    lcp
+}
+
+impl IndexedSequence {
+    // k is the k-mer length of the hash index; it must be at least 1
+    pub fn new(bases: Vec<Base>, k: usize) -> Self {
+        assert!(k > 0, "k-mer length must be at least 1");
+        let mut kmers: HashMap<Vec<Base>, Vec<usize>> = HashMap::new();
+        for (start, kmer) in bases.windows(k).enumerate() {
+            kmers.entry(kmer.to_vec()).or_default().push(start);
+        }
+        let suffix_array = build_suffix_array(&bases);
+        let lcp = build_lcp(&bases, &suffix_array);
+        IndexedSequence {
+            bases,
+            k,
+            kmers,
+            suffix_array,
+            lcp,
+        }
+    }
+
+    pub fn bases(&self) -> &[Base] {
+        &self.bases
+    }
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    pub fn suffix_array(&self) -> &[usize] {
+        &self.suffix_array
+    }
+    pub fn lcp(&self) -> &[usize] {
+        &self.lcp
+    }
+
+    // Compare the suffix at start with pattern, considering only the first pattern.len() bases
+    fn compare_prefix(&self, start: usize, pattern: &[Base]) -> Ordering {
+        let suffix = &self.bases[start..];
+        let prefix = &suffix[..suffix.len().min(pattern.len())];
+        prefix
+            .iter()
+            .map(|base| rank(*base))
+            .cmp(pattern.iter().map(|base| rank(*base)))
+    }
+
+    // The suffixes starting with pattern form a contiguous range of the suffix array
+    fn suffix_range(&self, pattern: &[Base]) -> (usize, usize) {
+        let low = self
+            .suffix_array
+            .partition_point(|&start| self.compare_prefix(start, pattern) == Ordering::Less);
+        let high = low
+            + self.suffix_array[low..]
+                .partition_point(|&start| self.compare_prefix(start, pattern) == Ordering::Equal);
+        (low, high)
+    }
+
+    // Start positions of all occurrences of pattern, in increasing order:
+    // a hash lookup for k-mers, otherwise O(m log n) binary search in the suffix array
+    pub fn occurrences(&self, pattern: &[Base]) -> Vec<usize> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        if pattern.len() == self.k {
+            return self.kmers.get(pattern).cloned().unwrap_or_default();
+        }
+        let (low, high) = self.suffix_range(pattern);
+        let mut positions = self.suffix_array[low..high].to_vec();
+        positions.sort_unstable();
+        positions
+    }
+    pub fn count(&self, pattern: &[Base]) -> usize {
+        if pattern.is_empty() {
+            return 0;
+        }
+        if pattern.len() == self.k {
+            return self.kmer_frequency(pattern);
+        }
+        let (low, high) = self.suffix_range(pattern);
+        high - low
+    }
+    pub fn contains(&self, pattern: &[Base]) -> bool {
+        self.count(pattern) > 0
+    }
+
+    // Number of occurrences of a k-mer; 0 for patterns of another length
+    pub fn kmer_frequency(&self, kmer: &[Base]) -> usize {
+        self.kmers.get(kmer).map_or(0, Vec::len)
+    }
+    // All distinct k-mers with their number of occurrences, most frequent first
+    // (ties in alphabetical order)
+    pub fn kmer_counts(&self) -> Vec<(&[Base], usize)> {
+        let mut counts: Vec<(&[Base], usize)> = self
+            .kmers
+            .iter()
+            .map(|(kmer, positions)| (kmer.as_slice(), positions.len()))
+            .collect();
+        counts.sort_by(|(kmer1, count1), (kmer2, count2)| {
+            count2.cmp(count1).then_with(|| {
+                kmer1
+                    .iter()
+                    .map(|base| rank(*base))
+                    .cmp(kmer2.iter().map(|base| rank(*base)))
+            })
+        });
+        counts
+    }
+
+    // The longest substring occurring at least twice (occurrences may overlap);
+    // None if no base occurs twice. Of several candidates, the alphabetically first is returned.
+    pub fn longest_repeated_substring(&self) -> Option<&[Base]> {
+        let (index, length) =
+            self.lcp
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, 0), |best, (index, length)| {
+                    if length > best.1 {
+                        (index, length)
+                    } else {
+                        best
+                    }
+                });
+        if length == 0 {
+            return None;
+        }
+        let start = self.suffix_array[index];
+        Some(&self.bases[start..start + length])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nucleotide::parse_sequence;
+    use crate::pattern::kmp_positions;
+
+    fn to_string(bases: &[Base]) -> String {
+        bases.iter().map(|base| base.to_char()).collect()
+    }
+
+    #[test]
+    fn suffix_and_lcp_arrays_are_correct() {
+        let index = IndexedSequence::new(parse_sequence("GATTACA").unwrap(), 2);
+        // suffixes in order: A, ACA, ATTACA, CA, GATTACA, TACA, TTACA
+        assert_eq!(index.suffix_array(), &[6, 4, 1, 5, 0, 3, 2]);
+        assert_eq!(index.lcp(), &[0, 1, 1, 0, 0, 0, 1]);
+        assert_eq!(to_string(index.longest_repeated_substring().unwrap()), "A");
+        let unique = IndexedSequence::new(parse_sequence("ACGT").unwrap(), 2);
+        assert!(unique.longest_repeated_substring().is_none());
+    }
+
+    #[test]
+    fn queries_agree_with_linear_search() {
+        // pseudo-random sequence from a linear congruential generator
+        let mut state: u32 = 12345;
+        let bases: Vec<Base> = (0..2000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                [Base::A, Base::C, Base::G, Base::T][(state >> 16) as usize % 4]
+            })
+            .collect();
+        let index = IndexedSequence::new(bases.clone(), 4);
+        for length in 1..8 {
+            for start in (0..1900).step_by(97) {
+                let pattern = &bases[start..start + length];
+                let expected = kmp_positions(&bases, pattern);
+                assert_eq!(index.occurrences(pattern), expected);
+                assert_eq!(index.count(pattern), expected.len());
+            }
+        }
+        let total: usize = index.kmer_counts().iter().map(|(_, count)| count).sum();
+        assert_eq!(total, bases.len() - 3);
+        assert!(!index.contains(&parse_sequence("ACGTN").unwrap()));
+
+        let repeat = index.longest_repeated_substring().unwrap();
+        assert!(kmp_positions(&bases, repeat).len() >= 2);
+        let longer = repeat.len() + 1;
+        assert!(bases
+            .windows(longer)
+            .all(|window| kmp_positions(&bases, window).len() == 1));
+    }
+}
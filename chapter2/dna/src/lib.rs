@@ -0,0 +1,101 @@
+// dna/src/lib.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Nucleotide sequences as used by dna_search, but read from real data:
+// lower and upper case, whitespace, IUPAC ambiguity codes and (multi-record) FASTA files.
+// Invalid input results in a DnaError with its position instead of a panic.
+use std::fmt;
+use std::io;
+
+pub mod fasta;
+pub mod index;
+pub mod nucleotide;
+pub mod pattern;
+pub mod translation;
+
+#[derive(Debug)]
+pub enum DnaError {
+    // A character that is not a IUPAC nucleotide code (lines and columns start at 1)
+    InvalidBase {
+        found: char,
+        line: usize,
+        column: usize,
+    },
+    // An ambiguity code such as N where only A, C, G or T is allowed
+    AmbiguousBase {
+        found: char,
+        line: usize,
+        column: usize,
+    },
+    // The number of nucleotides is not a multiple of three
+    IncompleteCodon {
+        trailing: usize,
+    },
+    // FASTA sequence data before the first header line
+    MissingHeader {
+        line: usize,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for DnaError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnaError::InvalidBase {
+                found,
+                line,
+                column,
+            } => write!(
+                formatter,
+                "invalid nucleotide {found:?} at line {line}, column {column}"
+            ),
+            DnaError::AmbiguousBase {
+                found,
+                line,
+                column,
+            } => write!(
+                formatter,
+                "ambiguous nucleotide {found:?} at line {line}, column {column}"
+            ),
+            DnaError::IncompleteCodon { trailing } => write!(
+                formatter,
+                "sequence ends with an incomplete codon of {trailing} nucleotide(s)"
+            ),
+            DnaError::MissingHeader { line } => {
+                write!(
+                    formatter,
+                    "sequence data without a FASTA header at line {line}"
+                )
+            }
+            DnaError::Io(error) => write!(formatter, "I/O error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for DnaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DnaError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DnaError {
+    fn from(error: io::Error) -> Self {
+        DnaError::Io(error)
+    }
+}
@@ -0,0 +1,336 @@
+// nucleotide.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use crate::DnaError;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum Nucleotide {
+    A,
+    C,
+    G,
+    T,
+}
+
+pub type Codon = (Nucleotide, Nucleotide, Nucleotide);
+pub type Gene = Vec<Codon>;
+
+impl Nucleotide {
+    pub const ALL: [Nucleotide; 4] = [Nucleotide::A, Nucleotide::C, Nucleotide::G, Nucleotide::T];
+
+    pub fn complement(self) -> Self {
+        match self {
+            Nucleotide::A => Nucleotide::T,
+            Nucleotide::C => Nucleotide::G,
+            Nucleotide::G => Nucleotide::C,
+            Nucleotide::T => Nucleotide::A,
+        }
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            Nucleotide::A => 'A',
+            Nucleotide::C => 'C',
+            Nucleotide::G => 'G',
+            Nucleotide::T => 'T',
+        }
+    }
+}
+
+impl fmt::Display for Nucleotide {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.to_char())
+    }
+}
+
+// A IUPAC nucleotide code: one of the four nucleotides or an ambiguity code
+// standing for a set of them (e.g. R = A or G, N = any nucleotide)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Base {
+    A,
+    C,
+    G,
+    T,
+    R,
+    Y,
+    S,
+    W,
+    K,
+    M,
+    B,
+    D,
+    H,
+    V,
+    N,
+}
+
+impl Base {
+    // Case-insensitive; U (RNA) is read as T
+    pub fn from_char(character: char) -> Option<Self> {
+        let base = match character.to_ascii_uppercase() {
+            'A' => Base::A,
+            'C' => Base::C,
+            'G' => Base::G,
+            'T' | 'U' => Base::T,
+            'R' => Base::R,
+            'Y' => Base::Y,
+            'S' => Base::S,
+            'W' => Base::W,
+            'K' => Base::K,
+            'M' => Base::M,
+            'B' => Base::B,
+            'D' => Base::D,
+            'H' => Base::H,
+            'V' => Base::V,
+            'N' => Base::N,
+            _ => return None,
+        };
+        Some(base)
+    }
+    pub fn to_char(self) -> char {
+        match self {
+            Base::A => 'A',
+            Base::C => 'C',
+            Base::G => 'G',
+            Base::T => 'T',
+            Base::R => 'R',
+            Base::Y => 'Y',
+            Base::S => 'S',
+            Base::W => 'W',
+            Base::K => 'K',
+            Base::M => 'M',
+            Base::B => 'B',
+            Base::D => 'D',
+            Base::H => 'H',
+            Base::V => 'V',
+            Base::N => 'N',
+        }
+    }
+    // The nucleotides a code stands for as bit set: A = 1, C = 2, G = 4, T = 8
+    fn mask(self) -> u8 {
+        match self {
+            Base::A => 0b0001,
+            Base::C => 0b0010,
+            Base::G => 0b0100,
+            Base::T => 0b1000,
+            Base::R => 0b0101,
+            Base::Y => 0b1010,
+            Base::S => 0b0110,
+            Base::W => 0b1001,
+            Base::K => 0b1100,
+            Base::M => 0b0011,
+            Base::B => 0b1110,
+            Base::D => 0b1101,
+            Base::H => 0b1011,
+            Base::V => 0b0111,
+            Base::N => 0b1111,
+        }
+    }
+    fn from_mask(mask: u8) -> Self {
+        match mask {
+            0b0001 => Base::A,
+            0b0010 => Base::C,
+            0b0100 => Base::G,
+            0b1000 => Base::T,
+            0b0101 => Base::R,
+            0b1010 => Base::Y,
+            0b0110 => Base::S,
+            0b1001 => Base::W,
+            0b1100 => Base::K,
+            0b0011 => Base::M,
+            0b1110 => Base::B,
+            0b1101 => Base::D,
+            0b1011 => Base::H,
+            0b0111 => Base::V,
+            _ => Base::N,
+        }
+    }
+    pub fn nucleotides(self) -> impl Iterator<Item = Nucleotide> {
+        let mask = self.mask();
+        Nucleotide::ALL
+            .into_iter()
+            .enumerate()
+            .filter(move |(index, _)| mask & (1 << index) != 0)
+            .map(|(_, nucleotide)| nucleotide)
+    }
+    // The nucleotide of an unambiguous code
+    pub fn nucleotide(self) -> Option<Nucleotide> {
+        match self {
+            Base::A => Some(Nucleotide::A),
+            Base::C => Some(Nucleotide::C),
+            Base::G => Some(Nucleotide::G),
+            Base::T => Some(Nucleotide::T),
+            _ => None,
+        }
+    }
+    pub fn is_ambiguous(self) -> bool {
+        self.nucleotide().is_none()
+    }
+    // Two codes match if they can stand for the same nucleotide
+    pub fn matches(self, other: Base) -> bool {
+        self.mask() & other.mask() != 0
+    }
+    pub fn complement(self) -> Self {
+        let mask = self.mask();
+        // swap A <-> T and C <-> G
+        Base::from_mask(
+            ((mask & 0b0001) << 3)
+                | ((mask & 0b1000) >> 3)
+                | ((mask & 0b0010) << 1)
+                | ((mask & 0b0100) >> 1),
+        )
+    }
+}
+
+impl From<Nucleotide> for Base {
+    fn from(nucleotide: Nucleotide) -> Self {
+        match nucleotide {
+            Nucleotide::A => Base::A,
+            Nucleotide::C => Base::C,
+            Nucleotide::G => Base::G,
+            Nucleotide::T => Base::T,
+        }
+    }
+}
+
+impl fmt::Display for Base {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.to_char())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+pub fn reverse_complement(bases: &[Base]) -> Vec<Base> {
+    bases.iter().rev().map(|base| base.complement()).collect()
+}
+
+// Parse text into IUPAC codes, ignoring whitespace (including line breaks)
+pub fn parse_sequence(text: &str) -> Result<Vec<Base>, DnaError> {
+    parse_lines(text, 1, |base, _, _| Ok(base))
+}
+
+// Parse text into nucleotides; ambiguity codes are rejected
+pub fn parse_nucleotides(text: &str) -> Result<Vec<Nucleotide>, DnaError> {
+    parse_lines(text, 1, |base, line, column| {
+        base.nucleotide().ok_or(DnaError::AmbiguousBase {
+            found: base.to_char(),
+            line,
+            column,
+        })
+    })
+}
+
+// Parse text into codons; the number of nucleotides must be a multiple of three
+pub fn parse_gene(text: &str) -> Result<Gene, DnaError> {
+    let nucleotides = parse_nucleotides(text)?;
+    match split_codons(&nucleotides) {
+        (gene, []) => Ok(gene),
+        (_, rest) => Err(DnaError::IncompleteCodon {
+            trailing: rest.len(),
+        }),
+    }
+}
+
+// Group nucleotides into codons, returning the (up to two) nucleotides left over
+pub fn split_codons(nucleotides: &[Nucleotide]) -> (Gene, &[Nucleotide]) {
+    let chunks = nucleotides.chunks_exact(3);
+    let rest = chunks.remainder();
+    let gene = chunks.map(|chunk| (chunk[0], chunk[1], chunk[2])).collect();
+    (gene, rest)
+}
+
+// Shared by the parsers above and the FASTA reader, which passes the number
+// of the text's first line so that errors refer to lines of the file
+pub(crate) fn parse_lines<T>(
+    text: &str,
+    first_line: usize,
+    mut convert: impl FnMut(Base, usize, usize) -> Result<T, DnaError>,
+) -> Result<Vec<T>, DnaError> {
+    let mut result = Vec::with_capacity(text.len());
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = first_line + line_index;
+        for (column_index, character) in line.chars().enumerate() {
+            if character.is_whitespace() {
+                continue;
+            }
+            let column = column_index + 1;
+            let base = Base::from_char(character).ok_or(DnaError::InvalidBase {
+                found: character,
+                line: line_number,
+                column,
+            })?;
+            result.push(convert(base, line_number, column)?);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_handles_case_whitespace_and_ambiguity_codes() {
+        let bases = parse_sequence("acgT\n  nRy u\n").unwrap();
+        let text: String = bases.iter().map(|base| base.to_char()).collect();
+        assert_eq!(text, "ACGTNRYT");
+        assert!(Base::R.matches(Base::A) && !Base::R.matches(Base::C));
+        assert_eq!(Base::R.complement(), Base::Y);
+        assert_eq!(Base::B.complement(), Base::V);
+        assert_eq!(Base::N.nucleotides().count(), 4);
+        let reversed: String = reverse_complement(&bases)
+            .iter()
+            .map(|base| base.to_char())
+            .collect();
+        assert_eq!(reversed, "ARYNACGT");
+    }
+
+    #[test]
+    fn parse_reports_positions() {
+        match parse_sequence("ACGT\nAC?T") {
+            Err(DnaError::InvalidBase {
+                found: '?',
+                line: 2,
+                column: 3,
+            }) => {}
+            other => panic!("unexpected result {other:?}"),
+        }
+        match parse_nucleotides("ACNT") {
+            Err(DnaError::AmbiguousBase {
+                found: 'N',
+                line: 1,
+                column: 3,
+            }) => {}
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_gene_rejects_incomplete_codons() {
+        let gene = parse_gene("ACG TGG").unwrap();
+        assert_eq!(gene[1], (Nucleotide::T, Nucleotide::G, Nucleotide::G));
+        assert!(matches!(
+            parse_gene("ACGTG"),
+            Err(DnaError::IncompleteCodon { trailing: 2 })
+        ));
+        let nucleotides = parse_nucleotides("ACGTG").unwrap();
+        let (gene, rest) = split_codons(&nucleotides);
+        assert_eq!((gene.len(), rest), (1, &[Nucleotide::T, Nucleotide::G][..]));
+    }
+}
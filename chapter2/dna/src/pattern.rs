@@ -0,0 +1,288 @@
+// pattern.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Finding every occurrence of a pattern in a sequence, on both strands.
+// Unlike linear_contains/binary_contains in dna_search, the positions are kept.
+// Exact matching compares IUPAC codes for equality (N only matches N);
+// approximate matching treats codes as matching if they can stand for the same
+// nucleotide, so ambiguity codes in the pattern work as wildcards.
+use crate::nucleotide::{reverse_complement, Base, Strand};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    // Zero-based, half-open coordinates on the forward strand
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    // Number of mismatches or edits; 0 for exact matches
+    pub distance: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExactAlgorithm {
+    KnuthMorrisPratt,
+    BoyerMooreHorspool,
+}
+
+// Start positions of all (possibly overlapping) occurrences of pattern in text
+pub fn kmp_positions<T: PartialEq>(text: &[T], pattern: &[T]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    if pattern.is_empty() {
+        return positions;
+    }
+    // failure[i]: length of the longest proper prefix of pattern[..=i] that is also its suffix
+    let mut failure = vec![0; pattern.len()];
+    let mut length = 0;
+    for i in 1..pattern.len() {
+        while length > 0 && pattern[i] != pattern[length] {
+            length = failure[length - 1];
+        }
+        if pattern[i] == pattern[length] {
+            length += 1;
+        }
+        failure[i] = length;
+    }
+    let mut matched = 0;
+    for (i, item) in text.iter().enumerate() {
+        while matched > 0 && *item != pattern[matched] {
+            matched = failure[matched - 1];
+        }
+        if *item == pattern[matched] {
+            matched += 1;
+        }
+        if matched == pattern.len() {
+            positions.push(i + 1 - pattern.len());
+            matched = failure[matched - 1];
+        }
+    }
+    positions
+}
+
+// Same result as kmp_positions, usually faster for long patterns because it skips ahead
+pub fn horspool_positions<T: Eq + Hash>(text: &[T], pattern: &[T]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let m = pattern.len();
+    if m == 0 || m > text.len() {
+        return positions;
+    }
+    // shift by the distance of the last occurrence of the window's last item from the pattern's end
+    let mut shift = HashMap::new();
+    for (i, item) in pattern[..m - 1].iter().enumerate() {
+        shift.insert(item, m - 1 - i);
+    }
+    let mut position = 0;
+    while position + m <= text.len() {
+        let window = &text[position..position + m];
+        if window
+            .iter()
+            .rev()
+            .zip(pattern.iter().rev())
+            .all(|(a, b)| a == b)
+        {
+            positions.push(position);
+        }
+        position += shift.get(&window[m - 1]).copied().unwrap_or(m);
+    }
+    positions
+}
+
+// Searching the reverse complement of the pattern on the forward strand
+// finds the occurrences of the pattern on the reverse strand
+fn both_strands(
+    text: &[Base],
+    pattern: &[Base],
+    search: impl Fn(&[Base], &[Base]) -> Vec<Hit>,
+) -> Vec<Hit> {
+    let mut hits = search(text, pattern);
+    let reverse_pattern = reverse_complement(pattern);
+    hits.extend(search(text, &reverse_pattern).into_iter().map(|hit| Hit {
+        strand: Strand::Reverse,
+        ..hit
+    }));
+    hits.sort_by_key(|hit| (hit.start, hit.strand == Strand::Reverse));
+    hits
+}
+
+pub fn find_exact(text: &[Base], pattern: &[Base], algorithm: ExactAlgorithm) -> Vec<Hit> {
+    both_strands(text, pattern, |text, pattern| {
+        let positions = match algorithm {
+            ExactAlgorithm::KnuthMorrisPratt => kmp_positions(text, pattern),
+            ExactAlgorithm::BoyerMooreHorspool => horspool_positions(text, pattern),
+        };
+        positions
+            .into_iter()
+            .map(|start| Hit {
+                start,
+                end: start + pattern.len(),
+                strand: Strand::Forward,
+                distance: 0,
+            })
+            .collect()
+    })
+}
+
+// Occurrences with at most max_mismatches substitutions (Hamming distance)
+pub fn find_with_mismatches(text: &[Base], pattern: &[Base], max_mismatches: usize) -> Vec<Hit> {
+    both_strands(text, pattern, |text, pattern| {
+        if pattern.is_empty() || pattern.len() > text.len() {
+            return Vec::new();
+        }
+        (0..=text.len() - pattern.len())
+            .filter_map(|start| {
+                let mut mismatches = 0;
+                for (a, b) in text[start..].iter().zip(pattern) {
+                    if !a.matches(*b) {
+                        mismatches += 1;
+                        if mismatches > max_mismatches {
+                            return None;
+                        }
+                    }
+                }
+                Some(Hit {
+                    start,
+                    end: start + pattern.len(),
+                    strand: Strand::Forward,
+                    distance: mismatches,
+                })
+            })
+            .collect()
+    })
+}
+
+// Occurrences with at most max_edits substitutions, insertions and deletions
+// (Sellers' algorithm: edit distance where the match may start anywhere in the text).
+// Neighbouring end positions usually belong to the same occurrence, so of each
+// run of consecutive end positions only the one with the fewest edits is reported.
+pub fn find_with_edits(text: &[Base], pattern: &[Base], max_edits: usize) -> Vec<Hit> {
+    both_strands(text, pattern, |text, pattern| {
+        let mut hits: Vec<Hit> = Vec::new();
+        if pattern.is_empty() {
+            return hits;
+        }
+        // column of the DP table for the current end position in the text:
+        // (edit distance, start in the text) for each prefix length of the pattern
+        let mut column: Vec<(usize, usize)> = (0..=pattern.len()).map(|i| (i, 0)).collect();
+        let mut previous_end: Option<usize> = None;
+        for (j, item) in text.iter().enumerate() {
+            let mut next = vec![(0, j + 1); pattern.len() + 1];
+            for i in 1..=pattern.len() {
+                let substitution_cost = usize::from(!pattern[i - 1].matches(*item));
+                let (diagonal, diagonal_start) = column[i - 1];
+                let (up, up_start) = next[i - 1];
+                let (left, left_start) = column[i];
+                let start_of_diagonal = if i == 1 { j } else { diagonal_start };
+                next[i] = [
+                    (diagonal + substitution_cost, start_of_diagonal),
+                    (up + 1, up_start),
+                    (left + 1, left_start),
+                ]
+                .into_iter()
+                .min_by_key(|(cost, start)| (*cost, std::cmp::Reverse(*start)))
+                .unwrap();
+            }
+            column = next;
+            let (distance, start) = column[pattern.len()];
+            if distance <= max_edits {
+                let hit = Hit {
+                    start,
+                    end: j + 1,
+                    strand: Strand::Forward,
+                    distance,
+                };
+                match hits.last_mut() {
+                    Some(last) if previous_end == Some(j) => {
+                        if distance < last.distance {
+                            *last = hit;
+                        }
+                    }
+                    _ => hits.push(hit),
+                }
+                previous_end = Some(j + 1);
+            }
+        }
+        hits
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nucleotide::parse_sequence;
+
+    #[test]
+    fn exact_algorithms_agree() {
+        let text = b"abracadabra abracadabra";
+        assert_eq!(kmp_positions(text, b"abra"), vec![0, 7, 12, 19]);
+        assert_eq!(horspool_positions(text, b"abra"), vec![0, 7, 12, 19]);
+        assert_eq!(kmp_positions(b"aaaaa", b"aa"), vec![0, 1, 2, 3]);
+        assert_eq!(horspool_positions(b"aaaaa", b"aa"), vec![0, 1, 2, 3]);
+        assert!(horspool_positions(b"ab", b"abc").is_empty());
+    }
+
+    #[test]
+    fn finds_exact_hits_on_both_strands() {
+        let text = parse_sequence("ACGTTTGAACGT").unwrap();
+        let pattern = parse_sequence("TTTG").unwrap();
+        for algorithm in [
+            ExactAlgorithm::KnuthMorrisPratt,
+            ExactAlgorithm::BoyerMooreHorspool,
+        ] {
+            let hits = find_exact(&text, &pattern, algorithm);
+            // the reverse complement CAAA does not occur, so all hits are forward
+            assert_eq!(hits.len(), 1);
+            assert_eq!((hits[0].start, hits[0].end), (3, 7));
+            // ACGT is its own reverse complement
+            let palindrome = parse_sequence("ACGT").unwrap();
+            let hits = find_exact(&text, &palindrome, algorithm);
+            let found: Vec<(usize, Strand)> =
+                hits.iter().map(|hit| (hit.start, hit.strand)).collect();
+            assert_eq!(
+                found,
+                vec![
+                    (0, Strand::Forward),
+                    (0, Strand::Reverse),
+                    (8, Strand::Forward),
+                    (8, Strand::Reverse)
+                ]
+            );
+        }
+        let reverse_only = parse_sequence("TTCA").unwrap();
+        let hits = find_exact(&text, &reverse_only, ExactAlgorithm::KnuthMorrisPratt);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].strand), (5, Strand::Reverse));
+    }
+
+    #[test]
+    fn finds_approximate_hits() {
+        let text = parse_sequence("GGGGACCTAGGGGG").unwrap();
+        let pattern = parse_sequence("ACGTA").unwrap();
+        let hits = find_with_mismatches(&text, &pattern, 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].distance), (4, 1));
+        assert!(find_with_mismatches(&text, &pattern, 0).is_empty());
+        let wildcard = parse_sequence("ACNTA").unwrap();
+        assert_eq!(find_with_mismatches(&text, &wildcard, 0).len(), 1);
+
+        // ACTA is ACGTA with the G deleted
+        let text = parse_sequence("GGGGACTAGGGG").unwrap();
+        let hits = find_with_edits(&text, &pattern, 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].end, hits[0].distance), (4, 8, 1));
+        assert!(find_with_mismatches(&text, &pattern, 1).is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+// translation.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Translation of codons to amino acids (one-letter codes, '*' for stop)
+// and search for open reading frames in all six reading frames.
+use crate::nucleotide::{reverse_complement, Base, Codon, Nucleotide, Strand};
+
+// The tables of NCBI's genetic codes list amino acids and start codons
+// for the 64 codons ordered TTT, TTC, TTA, TTG, TCT, ... (bases in order T, C, A, G)
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    amino_acids: &'static [u8; 64],
+    starts: &'static [u8; 64],
+}
+
+pub const GENETIC_CODES: [GeneticCode; 7] = [
+    GeneticCode {
+        id: 1,
+        name: "Standard",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: b"---M------**--*----M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 2,
+        name: "Vertebrate Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        starts: b"----------**--------------------MMMM----------**---M------------",
+    },
+    GeneticCode {
+        id: 3,
+        name: "Yeast Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: b"----------**----------------------MM---------------M------------",
+    },
+    GeneticCode {
+        id: 4,
+        name: "Mold, Protozoan, and Coelenterate Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: b"--MM------**-------M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 5,
+        name: "Invertebrate Mitochondrial",
+        amino_acids: b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        starts: b"---M------**--------------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 6,
+        name: "Ciliate, Dasycladacean and Hexamita Nuclear",
+        amino_acids: b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: b"--------------*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 11,
+        name: "Bacterial, Archaeal and Plant Plastid",
+        amino_acids: b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: b"---M------**--*----M------------MMMM---------------M------------",
+    },
+];
+
+pub const STOP: char = '*';
+// Translation of a codon containing ambiguity codes that does not determine the amino acid
+pub const UNKNOWN: char = 'X';
+
+fn table_index(nucleotide: Nucleotide) -> usize {
+    match nucleotide {
+        Nucleotide::T => 0,
+        Nucleotide::C => 1,
+        Nucleotide::A => 2,
+        Nucleotide::G => 3,
+    }
+}
+
+fn codon_index((first, second, third): Codon) -> usize {
+    table_index(first) * 16 + table_index(second) * 4 + table_index(third)
+}
+
+// All codons an ambiguous codon can stand for
+fn expand(codon: [Base; 3]) -> impl Iterator<Item = Codon> {
+    codon[0].nucleotides().flat_map(move |first| {
+        codon[1].nucleotides().flat_map(move |second| {
+            codon[2]
+                .nucleotides()
+                .map(move |third| (first, second, third))
+        })
+    })
+}
+
+impl GeneticCode {
+    pub fn standard() -> &'static GeneticCode {
+        &GENETIC_CODES[0]
+    }
+    // The NCBI translation table number, e.g. 11 for bacteria
+    pub fn by_id(id: u8) -> Option<&'static GeneticCode> {
+        GENETIC_CODES.iter().find(|code| code.id == id)
+    }
+
+    pub fn translate_codon(&self, codon: Codon) -> char {
+        self.amino_acids[codon_index(codon)] as char
+    }
+    pub fn is_start(&self, codon: Codon) -> bool {
+        self.starts[codon_index(codon)] == b'M'
+    }
+    pub fn is_stop(&self, codon: Codon) -> bool {
+        self.translate_codon(codon) == STOP
+    }
+    pub fn translate(&self, gene: &[Codon]) -> String {
+        gene.iter()
+            .map(|codon| self.translate_codon(*codon))
+            .collect()
+    }
+
+    // An ambiguous codon translates to an amino acid if all codons it stands for do,
+    // e.g. GCN is always alanine; otherwise it translates to UNKNOWN
+    pub fn translate_bases(&self, codon: [Base; 3]) -> char {
+        let mut amino_acids = expand(codon).map(|codon| self.translate_codon(codon));
+        let first = amino_acids.next().unwrap_or(UNKNOWN);
+        if amino_acids.all(|amino_acid| amino_acid == first) {
+            first
+        } else {
+            UNKNOWN
+        }
+    }
+    fn is_start_bases(&self, codon: [Base; 3]) -> bool {
+        expand(codon).all(|codon| self.is_start(codon))
+    }
+    pub fn translate_sequence(&self, bases: &[Base]) -> String {
+        bases
+            .chunks_exact(3)
+            .map(|codon| self.translate_bases([codon[0], codon[1], codon[2]]))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadingFrame {
+    pub strand: Strand,
+    // 0, 1 or 2: number of bases skipped at the start of the strand
+    pub offset: usize,
+}
+
+impl ReadingFrame {
+    pub fn all() -> [ReadingFrame; 6] {
+        let frame = |strand, offset| ReadingFrame { strand, offset };
+        [
+            frame(Strand::Forward, 0),
+            frame(Strand::Forward, 1),
+            frame(Strand::Forward, 2),
+            frame(Strand::Reverse, 0),
+            frame(Strand::Reverse, 1),
+            frame(Strand::Reverse, 2),
+        ]
+    }
+}
+
+// Translate one of the six reading frames of a sequence
+pub fn translate_frame(bases: &[Base], frame: ReadingFrame, code: &GeneticCode) -> String {
+    let strand = match frame.strand {
+        Strand::Forward => bases.to_vec(),
+        Strand::Reverse => reverse_complement(bases),
+    };
+    code.translate_sequence(strand.get(frame.offset..).unwrap_or_default())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenReadingFrame {
+    pub frame: ReadingFrame,
+    // Zero-based, half-open coordinates including the stop codon,
+    // always on the forward strand (also for frames on the reverse strand)
+    pub start: usize,
+    pub end: usize,
+    // Translation from the start codon (as M) up to, excluding, the stop codon
+    pub protein: String,
+}
+
+pub struct OrfOptions {
+    // Shorter proteins are not reported
+    pub min_protein_length: usize,
+    // If false, only ATG starts a reading frame; otherwise all start codons of the genetic code
+    pub alternative_starts: bool,
+}
+
+impl Default for OrfOptions {
+    fn default() -> Self {
+        OrfOptions {
+            min_protein_length: 30,
+            alternative_starts: false,
+        }
+    }
+}
+
+// Find the open reading frames in all six frames: from the first start codon
+// after a stop codon up to the next stop codon in the same frame.
+// Reading frames without a stop codon before the end of the sequence are not reported.
+pub fn find_orfs(
+    bases: &[Base],
+    code: &GeneticCode,
+    options: &OrfOptions,
+) -> Vec<OpenReadingFrame> {
+    let mut orfs = Vec::new();
+    let reverse = reverse_complement(bases);
+    for frame in ReadingFrame::all() {
+        let strand = match frame.strand {
+            Strand::Forward => bases,
+            Strand::Reverse => &reverse,
+        };
+        let mut open: Option<(usize, String)> = None;
+        let mut position = frame.offset;
+        while position + 3 <= strand.len() {
+            let codon = [strand[position], strand[position + 1], strand[position + 2]];
+            let amino_acid = code.translate_bases(codon);
+            if let Some((start, protein)) = open.as_mut() {
+                if amino_acid == STOP {
+                    if protein.len() >= options.min_protein_length {
+                        let end = position + 3;
+                        let (start, end) = match frame.strand {
+                            Strand::Forward => (*start, end),
+                            Strand::Reverse => (bases.len() - end, bases.len() - *start),
+                        };
+                        orfs.push(OpenReadingFrame {
+                            frame,
+                            start,
+                            end,
+                            protein: std::mem::take(protein),
+                        });
+                    }
+                    open = None;
+                } else {
+                    protein.push(amino_acid);
+                }
+            } else if is_start(code, codon, options) {
+                open = Some((position, String::from('M')));
+            }
+            position += 3;
+        }
+    }
+    orfs
+}
+
+fn is_start(code: &GeneticCode, codon: [Base; 3], options: &OrfOptions) -> bool {
+    if options.alternative_starts {
+        code.is_start_bases(codon)
+    } else {
+        codon == [Base::A, Base::T, Base::G]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nucleotide::{parse_gene, parse_sequence};
+
+    #[test]
+    fn translates_with_standard_and_alternative_codes() {
+        let gene = parse_gene("ATGTGGTGAAGATAA").unwrap();
+        assert_eq!(GeneticCode::standard().translate(&gene), "MW*R*");
+        assert_eq!(GeneticCode::by_id(2).unwrap().translate(&gene), "MWW**");
+        assert!(GeneticCode::by_id(11).unwrap().is_start(gene[0]));
+        assert!(GeneticCode::by_id(7).is_none());
+        let bases = parse_sequence("GCNTTYAAR").unwrap();
+        assert_eq!(GeneticCode::standard().translate_sequence(&bases), "AFK");
+        let bases = parse_sequence("NNNTAR").unwrap();
+        assert_eq!(GeneticCode::standard().translate_sequence(&bases), "X*");
+    }
+
+    #[test]
+    fn translates_all_six_frames() {
+        let bases = parse_sequence("ATGGCCTAA").unwrap();
+        let frames: Vec<String> = ReadingFrame::all()
+            .into_iter()
+            .map(|frame| translate_frame(&bases, frame, GeneticCode::standard()))
+            .collect();
+        // the reverse complement is TTAGGCCAT
+        assert_eq!(frames, vec!["MA*", "WP", "GL", "LGH", "*A", "RP"]);
+    }
+
+    #[test]
+    fn finds_orfs_on_both_strands() {
+        // forward: ATG AAA TAG at 2..11, reverse: ATG CCC TAA (reverse complement TTAGGGCAT) at 13..22
+        let bases = parse_sequence("CCATGAAATAGCCTTAGGGCATCC").unwrap();
+        let options = OrfOptions {
+            min_protein_length: 1,
+            alternative_starts: false,
+        };
+        let orfs = find_orfs(&bases, GeneticCode::standard(), &options);
+        assert_eq!(orfs.len(), 2);
+        assert_eq!(
+            (orfs[0].start, orfs[0].end, orfs[0].protein.as_str()),
+            (2, 11, "MK")
+        );
+        assert_eq!(orfs[0].frame.strand, Strand::Forward);
+        assert_eq!(
+            (orfs[1].start, orfs[1].end, orfs[1].protein.as_str()),
+            (13, 22, "MP")
+        );
+        assert_eq!(orfs[1].frame.strand, Strand::Reverse);
+        let long_only = OrfOptions {
+            min_protein_length: 3,
+            alternative_starts: true,
+        };
+        assert!(find_orfs(&bases, GeneticCode::standard(), &long_only).is_empty());
+    }
+}
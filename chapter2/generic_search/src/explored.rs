@@ -0,0 +1,349 @@
+// explored.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Back-ends for the explored set of dfs and bfs.
+// A HashSet<T> stores every state, which does not fit in RAM for state spaces
+// with hundreds of millions of states. The alternatives below trade exactness
+// for memory:
+// - FingerprintSet stores a 64-bit hash per state (exact up to hash collisions)
+// - BitStateSet sets a few bits per state (Holzmann's bit-state hashing),
+//   which may wrongly treat new states as explored and thus miss them
+// - PerfectHashSet uses a user-supplied injective index into a bitset (exact)
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+pub trait ExploredSet<T> {
+    // Mark the state as explored. Returns false if the state was (or is believed
+    // to be) explored already, true if it is new
+    fn insert(&mut self, state: &T) -> bool;
+    fn contains(&self, state: &T) -> bool;
+    // Number of states that were inserted as new
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // Expected fraction of the states reached so far that were wrongly treated as
+    // explored and therefore never expanded; zero for exact back-ends
+    fn omission_probability(&self) -> f64 {
+        0.0
+    }
+}
+
+// Allows passing an explored set by reference and inspecting it after the search
+impl<T, E: ExploredSet<T> + ?Sized> ExploredSet<T> for &mut E {
+    fn insert(&mut self, state: &T) -> bool {
+        (**self).insert(state)
+    }
+    fn contains(&self, state: &T) -> bool {
+        (**self).contains(state)
+    }
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+    fn omission_probability(&self) -> f64 {
+        (**self).omission_probability()
+    }
+}
+
+impl<T: Eq + Hash + Clone> ExploredSet<T> for HashSet<T> {
+    fn insert(&mut self, state: &T) -> bool {
+        HashSet::insert(self, state.clone())
+    }
+    fn contains(&self, state: &T) -> bool {
+        HashSet::contains(self, state)
+    }
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+}
+
+// DefaultHasher::new() always uses the same keys, so hashes are reproducible
+// between runs; the seed allows deriving independent hash functions
+fn hash_with_seed<T: Hash>(state: &T, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Stores 8 bytes per state instead of the state itself
+#[derive(Default)]
+pub struct FingerprintSet {
+    fingerprints: HashSet<u64>,
+}
+
+impl FingerprintSet {
+    pub fn new() -> Self {
+        FingerprintSet {
+            fingerprints: HashSet::new(),
+        }
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        FingerprintSet {
+            fingerprints: HashSet::with_capacity(capacity),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+    // The i-th new state shares the fingerprint of one of the i - 1 before it with
+    // probability (i - 1) / 2^64, so about n^2 / 2^65 of n states are omitted
+    pub fn expected_omissions(&self) -> f64 {
+        let n = self.fingerprints.len() as f64;
+        n * (n - 1.0) / 2_f64.powi(65)
+    }
+    pub fn omission_probability(&self) -> f64 {
+        let omissions = self.expected_omissions();
+        omissions / (self.fingerprints.len() as f64 + omissions)
+    }
+}
+
+impl<T: Hash> ExploredSet<T> for FingerprintSet {
+    fn insert(&mut self, state: &T) -> bool {
+        self.fingerprints.insert(hash_with_seed(state, 0))
+    }
+    fn contains(&self, state: &T) -> bool {
+        self.fingerprints.contains(&hash_with_seed(state, 0))
+    }
+    fn len(&self) -> usize {
+        FingerprintSet::len(self)
+    }
+    fn omission_probability(&self) -> f64 {
+        FingerprintSet::omission_probability(self)
+    }
+}
+
+// Bit-state hashing: each state sets hash_count bits in a fixed-size bit array.
+// A state whose bits are all set already is considered explored, even if it is not.
+pub struct BitStateSet {
+    bits: Vec<u64>,
+    number_of_bits: u64,
+    hash_count: u64,
+    bits_set: u64,
+    inserted: usize,
+    expected_omissions: f64,
+}
+
+impl BitStateSet {
+    // The number of bits is rounded up to a multiple of 64
+    pub fn new(number_of_bits: usize, hash_count: usize) -> Self {
+        assert!(number_of_bits > 0, "A bit-state set needs at least one bit");
+        assert!(
+            hash_count > 0,
+            "A bit-state set needs at least one hash function"
+        );
+        let words = number_of_bits.div_ceil(64);
+        BitStateSet {
+            bits: vec![0; words],
+            number_of_bits: (words * 64) as u64,
+            hash_count: hash_count as u64,
+            bits_set: 0,
+            inserted: 0,
+            expected_omissions: 0.0,
+        }
+    }
+    pub fn with_memory(bytes: usize, hash_count: usize) -> Self {
+        Self::new(bytes * 8, hash_count)
+    }
+    pub fn memory_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+    pub fn len(&self) -> usize {
+        self.inserted
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inserted == 0
+    }
+    // Probability that a state not seen so far hits only bits that are set already
+    pub fn false_positive_rate(&self) -> f64 {
+        (self.bits_set as f64 / self.number_of_bits as f64).powi(self.hash_count as i32)
+    }
+    // Estimated number of new states wrongly treated as explored so far: while the
+    // false positive rate is p, a state that got in stands for 1 / (1 - p) new states,
+    // p / (1 - p) of which were omitted
+    pub fn expected_omissions(&self) -> f64 {
+        self.expected_omissions
+    }
+    pub fn omission_probability(&self) -> f64 {
+        let omissions = self.expected_omissions;
+        if omissions == 0.0 {
+            return 0.0;
+        }
+        omissions / (self.inserted as f64 + omissions)
+    }
+    // Double hashing: the i-th index is h1 + i * h2 (mod number of bits)
+    fn hashes<T: Hash>(state: &T) -> (u64, u64) {
+        let h1 = hash_with_seed(state, 0);
+        let h2 = hash_with_seed(state, 1) | 1; // odd, so that indices differ
+        (h1, h2)
+    }
+    fn index(&self, (h1, h2): (u64, u64), i: u64) -> u64 {
+        h1.wrapping_add(i.wrapping_mul(h2)) % self.number_of_bits
+    }
+    fn is_set(&self, index: u64) -> bool {
+        self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0
+    }
+}
+
+impl<T: Hash> ExploredSet<T> for BitStateSet {
+    fn insert(&mut self, state: &T) -> bool {
+        let false_positive_rate = self.false_positive_rate();
+        let hashes = Self::hashes(state);
+        let mut new_state = false;
+        for i in 0..self.hash_count {
+            let index = self.index(hashes, i);
+            let mask = 1 << (index % 64);
+            let word = &mut self.bits[(index / 64) as usize];
+            if *word & mask == 0 {
+                *word |= mask;
+                self.bits_set += 1;
+                new_state = true;
+            }
+        }
+        if new_state {
+            self.inserted += 1;
+            self.expected_omissions += false_positive_rate / (1.0 - false_positive_rate);
+        }
+        new_state
+    }
+    fn contains(&self, state: &T) -> bool {
+        let hashes = Self::hashes(state);
+        (0..self.hash_count).all(|i| self.is_set(self.index(hashes, i)))
+    }
+    fn len(&self) -> usize {
+        BitStateSet::len(self)
+    }
+    fn omission_probability(&self) -> f64 {
+        BitStateSet::omission_probability(self)
+    }
+}
+
+// For state spaces that can be enumerated, e.g. (missionaries, cannibals, boat),
+// a perfect hash maps each state to a unique index below capacity,
+// so one bit per possible state suffices
+pub struct PerfectHashSet<F> {
+    bits: Vec<u64>,
+    capacity: usize,
+    inserted: usize,
+    index_of: F,
+}
+
+impl<F> PerfectHashSet<F> {
+    pub fn new(capacity: usize, index_of: F) -> Self {
+        PerfectHashSet {
+            bits: vec![0; capacity.div_ceil(64)],
+            capacity,
+            inserted: 0,
+            index_of,
+        }
+    }
+    pub fn memory_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+    pub fn len(&self) -> usize {
+        self.inserted
+    }
+    pub fn is_empty(&self) -> bool {
+        self.inserted == 0
+    }
+    fn checked_index<T>(&self, state: &T) -> usize
+    where
+        F: Fn(&T) -> usize,
+    {
+        let index = (self.index_of)(state);
+        assert!(
+            index < self.capacity,
+            "Perfect hash index {index} out of range 0..{}",
+            self.capacity
+        );
+        index
+    }
+}
+
+impl<T, F: Fn(&T) -> usize> ExploredSet<T> for PerfectHashSet<F> {
+    fn insert(&mut self, state: &T) -> bool {
+        let index = self.checked_index(state);
+        let mask = 1 << (index % 64);
+        let word = &mut self.bits[index / 64];
+        if *word & mask == 0 {
+            *word |= mask;
+            self.inserted += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn contains(&self, state: &T) -> bool {
+        let index = self.checked_index(state);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+    fn len(&self) -> usize {
+        PerfectHashSet::len(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_back_ends_agree() {
+        let mut hash_set = HashSet::<(u32, u32)>::new();
+        let mut fingerprints = FingerprintSet::new();
+        let mut perfect =
+            PerfectHashSet::new(100 * 100, |&(x, y): &(u32, u32)| (x * 100 + y) as usize);
+        for x in 0..100 {
+            for y in 0..100 {
+                let state = (x, y / 2);
+                let new_state = ExploredSet::insert(&mut hash_set, &state);
+                assert_eq!(fingerprints.insert(&state), new_state);
+                assert_eq!(perfect.insert(&state), new_state);
+            }
+        }
+        assert_eq!(ExploredSet::len(&hash_set), 5000);
+        assert_eq!(fingerprints.len(), 5000);
+        assert_eq!(perfect.len(), 5000);
+        assert!(fingerprints.contains(&(99_u32, 49_u32)));
+        assert!(!perfect.contains(&(99_u32, 50_u32)));
+        assert_eq!(
+            ExploredSet::<(u32, u32)>::omission_probability(&perfect),
+            0.0
+        );
+        assert!(fingerprints.omission_probability() < 1e-9);
+    }
+
+    #[test]
+    fn bit_state_set_reports_omission_probability() {
+        let mut roomy = BitStateSet::new(1 << 20, 3);
+        let mut cramped = BitStateSet::new(1 << 10, 3);
+        for state in 0..1000_u32 {
+            roomy.insert(&state);
+            cramped.insert(&state);
+        }
+        assert!(roomy.contains(&999_u32));
+        assert!(roomy.omission_probability() < 1e-6);
+        assert!(cramped.false_positive_rate() > 0.5);
+        // a nearly full bit array wrongly reports states as explored
+        let omitted = (1000 - cramped.len()) as f64;
+        assert!(omitted > 0.0);
+        assert!((cramped.expected_omissions() - omitted).abs() < omitted / 2.0);
+        assert!((cramped.omission_probability() - omitted / 1000.0).abs() < 0.1);
+    }
+}
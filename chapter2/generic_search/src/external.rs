@@ -0,0 +1,482 @@
+// external.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// External-memory (disk-backed) breadth-first search.
+// Instead of keeping the frontier and the explored set in memory, each BFS layer
+// is written to a file. Successors of a layer are collected in memory up to a
+// limit, sorted and written as "runs"; duplicates are removed when the runs are
+// merged and compared against the (sorted) file of all visited states.
+// This is known as delayed duplicate detection. If there are more runs than files
+// may be open at once, they are first merged in passes into fewer, longer runs.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// States must be stored on disk as records of a fixed number of bytes
+pub trait DiskState: Sized {
+    const SIZE: usize;
+    fn encode(&self, bytes: &mut [u8]);
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+// Big-endian, so that the byte order of the records equals the numeric order
+macro_rules! impl_disk_state {
+    ($($t:ty),*) => {
+        $(
+            impl DiskState for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+                fn encode(&self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_be_bytes());
+                }
+                fn decode(bytes: &[u8]) -> Self {
+                    <$t>::from_be_bytes(bytes.try_into().expect("Record has the wrong size"))
+                }
+            }
+        )*
+    };
+}
+impl_disk_state!(u8, u16, u32, u64, u128);
+
+pub struct ExternalBfsConfig {
+    directory: PathBuf,
+    max_states_in_memory: usize,
+    max_open_runs: usize,
+}
+
+impl ExternalBfsConfig {
+    // Temporary files are created in a new sub-directory of directory,
+    // which is removed again when the search is finished
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        ExternalBfsConfig {
+            directory: directory.into(),
+            max_states_in_memory: 1 << 20,
+            max_open_runs: 256,
+        }
+    }
+    // Number of generated successors that are buffered before a sorted run is written
+    pub fn max_states_in_memory(mut self, max_states_in_memory: usize) -> Self {
+        self.max_states_in_memory = max_states_in_memory.max(1);
+        self
+    }
+    // Number of runs that are read at the same time when merging, well below the limit
+    // of open files of the process (often 1024); more runs are merged in several passes
+    pub fn max_open_runs(mut self, max_open_runs: usize) -> Self {
+        self.max_open_runs = max_open_runs.max(2);
+        self
+    }
+}
+
+pub struct ExternalBfsResult<T> {
+    // Shortest path from the initial state to the first goal found, if any
+    pub path: Option<Vec<T>>,
+    // Number of states first reached at each depth, starting with the initial state at depth 0.
+    // If no goal was found, this is the complete breadth-first enumeration of the state space.
+    pub layer_sizes: Vec<u64>,
+}
+
+impl<T> ExternalBfsResult<T> {
+    pub fn states_visited(&self) -> u64 {
+        self.layer_sizes.iter().sum()
+    }
+}
+
+pub fn external_bfs<T: Copy + Eq + DiskState>(
+    initial: T,
+    goal_test: impl Fn(&T) -> bool,
+    successors: impl Fn(&T) -> Vec<T>,
+    config: &ExternalBfsConfig,
+) -> io::Result<ExternalBfsResult<T>> {
+    assert!(T::SIZE > 0, "States must be encoded in at least one byte");
+    let work = WorkDirectory::create(&config.directory)?;
+    let mut layer_sizes = vec![1];
+    let mut record = vec![0; T::SIZE];
+    initial.encode(&mut record);
+    write_records(&work.layer(0), [record.as_slice()])?;
+    write_records(&work.visited(), [record.as_slice()])?;
+    if goal_test(&initial) {
+        return Ok(ExternalBfsResult {
+            path: Some(vec![initial]),
+            layer_sizes,
+        });
+    }
+    let mut depth = 0;
+    loop {
+        let runs = write_successor_runs::<T>(&work, depth, &successors, config)?;
+        let runs = reduce_runs::<T>(&work, runs, config.max_open_runs)?;
+        let (new_states, goal) = merge_new_layer::<T>(&work, depth + 1, &runs, &goal_test)?;
+        for run in &runs {
+            fs::remove_file(run)?;
+        }
+        depth += 1;
+        if new_states == 0 {
+            return Ok(ExternalBfsResult {
+                path: None,
+                layer_sizes,
+            });
+        }
+        layer_sizes.push(new_states);
+        if let Some(goal) = goal {
+            let path = reconstruct_path(&work, depth, goal, &successors)?;
+            return Ok(ExternalBfsResult {
+                path: Some(path),
+                layer_sizes,
+            });
+        }
+    }
+}
+
+// Distinguishes the work directories of searches running at the same time
+static SEARCH_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Removes all temporary files, also if the search fails with an error
+struct WorkDirectory {
+    path: PathBuf,
+}
+
+impl WorkDirectory {
+    fn create(parent: &Path) -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let search = SEARCH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = parent.join(format!(
+            "external_bfs_{}_{search}_{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(WorkDirectory { path })
+    }
+    fn layer(&self, depth: usize) -> PathBuf {
+        self.path.join(format!("layer_{depth}.bin"))
+    }
+    fn visited(&self) -> PathBuf {
+        self.path.join("visited.bin")
+    }
+    fn run(&self, number: usize) -> PathBuf {
+        self.path.join(format!("run_{number}.bin"))
+    }
+}
+
+impl Drop for WorkDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn write_records<'a>(path: &Path, records: impl IntoIterator<Item = &'a [u8]>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for record in records {
+        writer.write_all(record)?;
+    }
+    writer.flush()
+}
+
+// Reads the next record into buffer; returns false at the end of the file
+fn read_record(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+// Expand every state of the layer at depth and write its successors as sorted,
+// duplicate-free runs of at most max_states_in_memory states each
+fn write_successor_runs<T: DiskState>(
+    work: &WorkDirectory,
+    depth: usize,
+    successors: &impl Fn(&T) -> Vec<T>,
+    config: &ExternalBfsConfig,
+) -> io::Result<Vec<PathBuf>> {
+    let mut runs = Vec::new();
+    let mut buffer = Vec::<u8>::with_capacity(config.max_states_in_memory.min(1 << 16) * T::SIZE);
+    let mut reader = BufReader::new(File::open(work.layer(depth))?);
+    let mut record = vec![0; T::SIZE];
+    while read_record(&mut reader, &mut record)? {
+        for child in successors(&T::decode(&record)) {
+            let start = buffer.len();
+            buffer.resize(start + T::SIZE, 0);
+            child.encode(&mut buffer[start..]);
+            if buffer.len() >= config.max_states_in_memory * T::SIZE {
+                runs.push(flush_run::<T>(work, runs.len(), &mut buffer)?);
+            }
+        }
+    }
+    if !buffer.is_empty() {
+        runs.push(flush_run::<T>(work, runs.len(), &mut buffer)?);
+    }
+    Ok(runs)
+}
+
+fn flush_run<T: DiskState>(
+    work: &WorkDirectory,
+    number: usize,
+    buffer: &mut Vec<u8>,
+) -> io::Result<PathBuf> {
+    let mut records: Vec<&[u8]> = buffer.chunks(T::SIZE).collect();
+    records.sort_unstable();
+    records.dedup();
+    let path = work.run(number);
+    write_records(&path, records)?;
+    buffer.clear();
+    Ok(path)
+}
+
+// Merges the runs in passes of max_open_runs runs each, until there are no more than
+// max_open_runs left, so that they can be merged with the visited states in one go
+fn reduce_runs<T: DiskState>(
+    work: &WorkDirectory,
+    mut runs: Vec<PathBuf>,
+    max_open_runs: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let mut next_run = runs.len();
+    while runs.len() > max_open_runs {
+        let mut merged = Vec::new();
+        for group in runs.chunks(max_open_runs) {
+            if let [run] = group {
+                merged.push(run.clone());
+                continue;
+            }
+            let path = work.run(next_run);
+            next_run += 1;
+            let mut writer = BufWriter::new(File::create(&path)?);
+            merge_runs(group, T::SIZE, |record| writer.write_all(record))?;
+            writer.flush()?;
+            for run in group {
+                fs::remove_file(run)?;
+            }
+            merged.push(path);
+        }
+        runs = merged;
+    }
+    Ok(runs)
+}
+
+// k-way merge of sorted runs of records of the given size:
+// emit is called for every distinct record, in order
+fn merge_runs(
+    runs: &[PathBuf],
+    size: usize,
+    mut emit: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut run_readers = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (index, run) in runs.iter().enumerate() {
+        let mut reader = BufReader::new(File::open(run)?);
+        let mut record = vec![0; size];
+        if read_record(&mut reader, &mut record)? {
+            heap.push(Reverse((record, index)));
+        }
+        run_readers.push(reader);
+    }
+    let mut last: Option<Vec<u8>> = None;
+    while let Some(Reverse((record, index))) = heap.pop() {
+        let mut next = vec![0; size];
+        if read_record(&mut run_readers[index], &mut next)? {
+            heap.push(Reverse((next, index)));
+        }
+        if last.as_ref() == Some(&record) {
+            continue; // duplicate from another run
+        }
+        emit(&record)?;
+        last = Some(record);
+    }
+    Ok(())
+}
+
+// Merge of the runs, dropping duplicates and states visited before.
+// Writes the new layer and the new visited file (old visited states plus the new layer).
+// Returns the number of new states and the first new goal state, if any.
+fn merge_new_layer<T: DiskState>(
+    work: &WorkDirectory,
+    depth: usize,
+    runs: &[PathBuf],
+    goal_test: &impl Fn(&T) -> bool,
+) -> io::Result<(u64, Option<T>)> {
+    let mut visited_reader = BufReader::new(File::open(work.visited())?);
+    let mut visited_record = vec![0; T::SIZE];
+    let mut visited_left = read_record(&mut visited_reader, &mut visited_record)?;
+    let merged_visited = work.path.join("visited_merged.bin");
+    let mut visited_writer = BufWriter::new(File::create(&merged_visited)?);
+    let mut layer_writer = BufWriter::new(File::create(work.layer(depth))?);
+    let mut new_states = 0;
+    let mut goal = None;
+    merge_runs(runs, T::SIZE, |record| {
+        // copy all smaller visited states, then check if the state was visited before
+        while visited_left && visited_record.as_slice() < record {
+            visited_writer.write_all(&visited_record)?;
+            visited_left = read_record(&mut visited_reader, &mut visited_record)?;
+        }
+        if !(visited_left && visited_record == record) {
+            layer_writer.write_all(record)?;
+            visited_writer.write_all(record)?;
+            new_states += 1;
+            if goal.is_none() {
+                let state = T::decode(record);
+                if goal_test(&state) {
+                    goal = Some(state);
+                }
+            }
+        }
+        Ok(())
+    })?;
+    while visited_left {
+        visited_writer.write_all(&visited_record)?;
+        visited_left = read_record(&mut visited_reader, &mut visited_record)?;
+    }
+    layer_writer.flush()?;
+    visited_writer.flush()?;
+    drop(visited_writer);
+    fs::rename(merged_visited, work.visited())?;
+    Ok((new_states, goal))
+}
+
+// Walk back through the layer files: for each layer, find a state that has
+// the current state of the path as a successor
+fn reconstruct_path<T: Copy + Eq + DiskState>(
+    work: &WorkDirectory,
+    depth: usize,
+    goal: T,
+    successors: &impl Fn(&T) -> Vec<T>,
+) -> io::Result<Vec<T>> {
+    let mut path = vec![goal];
+    let mut record = vec![0; T::SIZE];
+    for layer in (0..depth).rev() {
+        let target = *path.last().unwrap();
+        let mut reader = BufReader::new(File::open(work.layer(layer))?);
+        let mut parent = None;
+        while read_record(&mut reader, &mut record)? {
+            let state = T::decode(&record);
+            if successors(&state).contains(&target) {
+                parent = Some(state);
+                break;
+            }
+        }
+        match parent {
+            Some(parent) => path.push(parent),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("No predecessor found in layer {layer}"),
+                ))
+            }
+        }
+    }
+    path.reverse();
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bfs, node_to_path};
+    use std::collections::HashSet;
+
+    // states are (x, y) on a 30x30 grid encoded as 30 * x + y, moving right, down or diagonally
+    fn grid_successors(state: &u32) -> Vec<u32> {
+        let (x, y) = (state / 30, state % 30);
+        let mut successors = Vec::new();
+        if x < 29 {
+            successors.push(state + 30);
+        }
+        if y < 29 {
+            successors.push(state + 1);
+        }
+        if x < 29 && y < 29 {
+            successors.push(state + 31);
+        }
+        successors
+    }
+
+    #[test]
+    fn external_bfs_finds_shortest_path() {
+        let goal = 29 * 30 + 15;
+        let config = ExternalBfsConfig::new(std::env::temp_dir()).max_states_in_memory(7);
+        let result = external_bfs(0_u32, |state| *state == goal, grid_successors, &config).unwrap();
+        let path = result.path.unwrap();
+        let in_memory = node_to_path(
+            &bfs(
+                0_u32,
+                |state| *state == goal,
+                grid_successors,
+                HashSet::new(),
+            )
+            .unwrap(),
+        );
+        assert_eq!(path.len(), in_memory.len());
+        assert_eq!((path[0], path[path.len() - 1]), (0, goal));
+        for step in path.windows(2) {
+            assert!(grid_successors(&step[0]).contains(&step[1]));
+        }
+        assert_eq!(result.layer_sizes.len(), path.len());
+    }
+
+    #[test]
+    fn external_bfs_enumerates_state_space() {
+        let config = ExternalBfsConfig::new(std::env::temp_dir()).max_states_in_memory(50);
+        let result = external_bfs(0_u32, |_| false, grid_successors, &config).unwrap();
+        assert!(result.path.is_none());
+        assert_eq!(result.states_visited(), 900);
+        // with diagonal moves, depth d contains the states with max(x, y) == d
+        assert_eq!(result.layer_sizes.len(), 30);
+        assert_eq!(result.layer_sizes[29], 59);
+    }
+
+    #[test]
+    fn external_bfs_merges_many_runs_in_passes() {
+        // layers of up to 59 states with up to 3 successors each make dozens of runs of
+        // 2 states, so merging 3 at a time takes several passes
+        let config = ExternalBfsConfig::new(std::env::temp_dir())
+            .max_states_in_memory(2)
+            .max_open_runs(3);
+        let result = external_bfs(0_u32, |_| false, grid_successors, &config).unwrap();
+        assert_eq!(result.states_visited(), 900);
+        assert_eq!(result.layer_sizes.len(), 30);
+        assert_eq!(result.layer_sizes[29], 59);
+    }
+
+    #[test]
+    fn runs_are_reduced_to_max_open_runs() {
+        let work = WorkDirectory::create(&std::env::temp_dir()).unwrap();
+        let runs: Vec<PathBuf> = (0..10_u32)
+            .map(|number| {
+                // run number contains number, number + 1 and number + 2, so runs overlap
+                let path = work.run(number as usize);
+                let records: Vec<[u8; 4]> = (number..number + 3)
+                    .map(|state| state.to_be_bytes())
+                    .collect();
+                write_records(&path, records.iter().map(|record| record.as_slice())).unwrap();
+                path
+            })
+            .collect();
+        let runs = reduce_runs::<u32>(&work, runs, 3).unwrap();
+        // 10 runs, then 4, then 2
+        assert_eq!(runs.len(), 2);
+        let mut states = Vec::new();
+        merge_runs(&runs, 4, |record| {
+            states.push(u32::decode(record));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(states, (0..12).collect::<Vec<u32>>());
+        assert_eq!(fs::read_dir(&work.path).unwrap().count(), 2);
+    }
+}
@@ -14,13 +14,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use core::cmp::Ordering;
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::collections::VecDeque;
-use std::collections::BinaryHeap;
 use std::hash::Hash;
 use std::rc::Rc;
 
+pub mod explored;
+pub mod external;
+pub mod stepper;
+use explored::ExploredSet;
+use stepper::{AstarSearch, UninformedSearch};
+
 pub fn linear_contains<'a, T: 'a + PartialEq>( iterable: impl IntoIterator<Item = &'a T>, key: &T) -> bool {
     for item in iterable.into_iter() {
         if item == key {
@@ -107,85 +109,32 @@ pub fn node_to_path<T: PartialOrd + Eq + Copy>(node: &Node<T>) -> Vec<T> {
     path.into_iter().rev().collect()
 }
 
-pub fn dfs<T: PartialOrd + Copy + Eq + Hash>
-    (initial: T, goal_test: impl Fn(&T) -> bool, successors: impl Fn(&T) -> Vec<T>)
+// explored is the back-end for the states seen so far (see module explored): HashSet::new()
+// for the exact one of the book, or e.g. &mut FingerprintSet to inspect it after the search
+pub fn dfs<T: PartialOrd + Copy + Eq>
+    (initial: T, goal_test: impl Fn(&T) -> bool, successors: impl Fn(&T) -> Vec<T>, explored: impl ExploredSet<T>)
     -> Option<Rc<Node<T>>> {
-    // frontier is where we've yet to go
-    let mut frontier = Vec::<Rc<Node<T>>>::new();
-    let mut explored = HashSet::<T>::new();
-    frontier.push(Rc::new(Node::<T>::new(initial,None)));
-    explored.insert(initial);
-    while let Some(current_node) = frontier.pop() {
-        let current_state = current_node.state;
-        // if we found the goal, we're done
-        if goal_test(&current_state) {
-            return Some(current_node);
-        }
-        // check where we can go next and haven't explored
-        for child in successors(&current_state) {
-            if !explored.contains(&child) {
-                explored.insert(child);
-                frontier.push(Rc::new(Node::<T>::new(child,Some(Rc::clone(&current_node)))));
-            }
-        }
-    }
-    None // went through everything and never found goal
+    // see module stepper for the search loop
+    UninformedSearch::dfs_with(initial, goal_test, successors, explored).run()
 }
 
-pub fn bfs<T: PartialOrd + Copy + Eq + Hash>
-    (initial: T, goal_test: impl Fn(&T) -> bool, successors: impl Fn(&T) -> Vec<T>)
+// explored as for dfs
+pub fn bfs<T: PartialOrd + Copy + Eq>
+    (initial: T, goal_test: impl Fn(&T) -> bool, successors: impl Fn(&T) -> Vec<T>, explored: impl ExploredSet<T>)
     -> Option<Rc<Node<T>>> {
-    // frontier is where we've yet to go
-    let mut frontier = VecDeque::<Rc<Node<T>>>::new();
-    let mut explored = HashSet::<T>::new();
-    frontier.push_back(Rc::new(Node::<T>::new(initial,None)));
-    explored.insert(initial);
-    while let Some(current_node) = frontier.pop_front() {
-        let current_state = current_node.state;
-        // if we found the goal, we're done
-        if goal_test(&current_state) {
-            return Some(current_node);
-        }
-        // check where we can go next and haven't explored
-        for child in successors(&current_state) {
-            if !explored.contains(&child) {
-                explored.insert(child);
-                frontier.push_back(Rc::new(Node::<T>::new(child,Some(Rc::clone(&current_node)))));
-            }
-        }
-    }
-    None // went through everything and never found goal
+    UninformedSearch::bfs_with(initial, goal_test, successors, explored).run()
 }
 
 pub fn astar<T: PartialOrd + Copy + Eq + Hash>
     (initial: T, goal_test: impl Fn(&T) -> bool, successors: impl Fn(&T) -> Vec<T>, heuristic: impl Fn(&T) -> f64)
     -> Option<Rc<Node<T>>> {
-    // frontier is where we've yet to go
-    let mut frontier = BinaryHeap::<Rc<Node<T>>>::new();
-    let mut explored = HashMap::<T,f64>::new();
-    frontier.push(Rc::new(Node::<T>::new_with_cost(initial,None, 0.0, heuristic(&initial))));
-    explored.insert(initial, 0.0);
-    while let Some(current_node) = frontier.pop() {
-        let current_state = current_node.state;
-        // if we found the goal, we're done
-        if goal_test(&current_state) {
-            return Some(current_node);
-        }
-        // check where we can go next and haven't explored
-        for child in successors(&current_state) {
-            let new_cost = current_node.cost + 1.0;
-            if !explored.contains_key(&child) || explored.get(&child).unwrap() > &new_cost {
-                explored.insert(child,new_cost);
-                frontier.push(Rc::new(Node::<T>::new_with_cost(child,Some(Rc::clone(&current_node)), new_cost, heuristic(&child))));
-            }
-        }
-    }
-    None // went through everything and never found goal
+    AstarSearch::new(initial, goal_test, successors, heuristic).run()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn linear_contains_works() {
@@ -200,4 +149,36 @@ mod tests {
         assert_eq!( binary_contains(&vec!("a".to_string(), "d".to_string(), "e".to_string(), "f".to_string(), "z".to_string()),&"f".to_string()), true);
         assert_eq!( binary_contains(&vec!("john".to_string(), "mark".to_string(), "ronald".to_string(), "sarah".to_string()),&"sheila".to_string()), false);
     }
+
+    // states are (x, y) on a 20x20 grid, moving right or down
+    fn grid_successors(&(x, y): &(u32, u32)) -> Vec<(u32, u32)> {
+        let mut successors = Vec::new();
+        if x < 19 {
+            successors.push((x + 1, y));
+        }
+        if y < 19 {
+            successors.push((x, y + 1));
+        }
+        successors
+    }
+
+    #[test]
+    fn explored_back_ends_work_with_dfs_and_bfs() {
+        let goal_test = |state: &(u32, u32)| *state == (19, 19);
+        let shortest = node_to_path(&bfs((0, 0), goal_test, grid_successors, HashSet::new()).unwrap());
+        assert_eq!(shortest.len(), 39);
+
+        let mut fingerprints = explored::FingerprintSet::new();
+        let path = node_to_path(&bfs((0, 0), goal_test, grid_successors, &mut fingerprints).unwrap());
+        assert_eq!(path.len(), 39);
+
+        let mut perfect = explored::PerfectHashSet::new(400, |&(x, y): &(u32, u32)| (x * 20 + y) as usize);
+        let path = node_to_path(&dfs((0, 0), goal_test, grid_successors, &mut perfect).unwrap());
+        assert_eq!(path.last(), Some(&(19, 19)));
+
+        let mut bit_state = explored::BitStateSet::new(1 << 16, 2);
+        assert!(dfs((0, 0), goal_test, grid_successors, &mut bit_state).is_some());
+        assert!(bit_state.omission_probability() < 1e-3);
+        assert!(dfs((0, 0), |_| false, grid_successors, &mut explored::FingerprintSet::new()).is_none());
+    }
 }
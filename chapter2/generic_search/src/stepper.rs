@@ -0,0 +1,339 @@
+// stepper.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Step-by-step versions of dfs, bfs and astar.
+// Each call of step() does one unit of work and reports it as a SearchEvent,
+// so callers can pause a search, inspect its frontier, animate its progress
+// or interleave several searches. The steppers are also iterators over their events.
+use crate::explored::ExploredSet;
+use crate::{node_to_path, Node};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq)]
+pub enum SearchEvent<T> {
+    // The state was taken from the frontier and its successors were generated
+    Expanded(T),
+    // A successor not explored before was added to the frontier
+    Generated(T),
+    // The state taken from the frontier passed the goal test; contains the path to it
+    Found(Vec<T>),
+}
+
+// dfs and bfs only differ in the end of the frontier they take the next node from
+pub struct UninformedSearch<T: PartialOrd + Copy + Eq, G, S, E> {
+    frontier: VecDeque<Rc<Node<T>>>,
+    last_in_first_out: bool,
+    explored: E,
+    goal_test: G,
+    successors: S,
+    generated: VecDeque<T>,
+    solution: Option<Rc<Node<T>>>,
+    done: bool,
+}
+
+impl<T, G, S> UninformedSearch<T, G, S, HashSet<T>>
+where
+    T: PartialOrd + Copy + Eq + Hash,
+    G: Fn(&T) -> bool,
+    S: Fn(&T) -> Vec<T>,
+{
+    pub fn dfs(initial: T, goal_test: G, successors: S) -> Self {
+        Self::dfs_with(initial, goal_test, successors, HashSet::new())
+    }
+    pub fn bfs(initial: T, goal_test: G, successors: S) -> Self {
+        Self::bfs_with(initial, goal_test, successors, HashSet::new())
+    }
+}
+
+impl<T, G, S, E> UninformedSearch<T, G, S, E>
+where
+    T: PartialOrd + Copy + Eq,
+    G: Fn(&T) -> bool,
+    S: Fn(&T) -> Vec<T>,
+    E: ExploredSet<T>,
+{
+    pub fn dfs_with(initial: T, goal_test: G, successors: S, explored: E) -> Self {
+        Self::new(initial, goal_test, successors, explored, true)
+    }
+    pub fn bfs_with(initial: T, goal_test: G, successors: S, explored: E) -> Self {
+        Self::new(initial, goal_test, successors, explored, false)
+    }
+    fn new(
+        initial: T,
+        goal_test: G,
+        successors: S,
+        mut explored: E,
+        last_in_first_out: bool,
+    ) -> Self {
+        explored.insert(&initial);
+        UninformedSearch {
+            frontier: VecDeque::from([Rc::new(Node::new(initial, None))]),
+            last_in_first_out,
+            explored,
+            goal_test,
+            successors,
+            generated: VecDeque::new(),
+            solution: None,
+            done: false,
+        }
+    }
+
+    // Returns None once the goal was found or the frontier is empty
+    pub fn step(&mut self) -> Option<SearchEvent<T>> {
+        if let Some(child) = self.generated.pop_front() {
+            return Some(SearchEvent::Generated(child));
+        }
+        if self.done {
+            return None;
+        }
+        let next = if self.last_in_first_out {
+            self.frontier.pop_back()
+        } else {
+            self.frontier.pop_front()
+        };
+        let Some(current_node) = next else {
+            self.done = true;
+            return None;
+        };
+        let current_state = current_node.state;
+        if (self.goal_test)(&current_state) {
+            self.done = true;
+            let path = node_to_path(&current_node);
+            self.solution = Some(current_node);
+            return Some(SearchEvent::Found(path));
+        }
+        for child in (self.successors)(&current_state) {
+            if self.explored.insert(&child) {
+                self.frontier
+                    .push_back(Rc::new(Node::new(child, Some(Rc::clone(&current_node)))));
+                self.generated.push_back(child);
+            }
+        }
+        Some(SearchEvent::Expanded(current_state))
+    }
+
+    // Steps until the search is done and returns the goal node, if any
+    pub fn run(&mut self) -> Option<Rc<Node<T>>> {
+        while self.step().is_some() {}
+        self.solution.clone()
+    }
+
+    // The states in the frontier, in the order they would be taken from it
+    pub fn frontier(&self) -> Vec<T> {
+        let states = self.frontier.iter().map(|node| node.state);
+        if self.last_in_first_out {
+            states.rev().collect()
+        } else {
+            states.collect()
+        }
+    }
+    pub fn explored(&self) -> &E {
+        &self.explored
+    }
+    pub fn solution(&self) -> Option<&Rc<Node<T>>> {
+        self.solution.as_ref()
+    }
+    pub fn is_done(&self) -> bool {
+        self.done && self.generated.is_empty()
+    }
+}
+
+impl<T, G, S, E> Iterator for UninformedSearch<T, G, S, E>
+where
+    T: PartialOrd + Copy + Eq,
+    G: Fn(&T) -> bool,
+    S: Fn(&T) -> Vec<T>,
+    E: ExploredSet<T>,
+{
+    type Item = SearchEvent<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step()
+    }
+}
+
+pub struct AstarSearch<T: PartialOrd + Copy + Eq, G, S, H> {
+    frontier: BinaryHeap<Rc<Node<T>>>,
+    explored: HashMap<T, f64>,
+    goal_test: G,
+    successors: S,
+    heuristic: H,
+    generated: VecDeque<T>,
+    solution: Option<Rc<Node<T>>>,
+    done: bool,
+}
+
+impl<T, G, S, H> AstarSearch<T, G, S, H>
+where
+    T: PartialOrd + Copy + Eq + Hash,
+    G: Fn(&T) -> bool,
+    S: Fn(&T) -> Vec<T>,
+    H: Fn(&T) -> f64,
+{
+    pub fn new(initial: T, goal_test: G, successors: S, heuristic: H) -> Self {
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Rc::new(Node::new_with_cost(
+            initial,
+            None,
+            0.0,
+            heuristic(&initial),
+        )));
+        AstarSearch {
+            frontier,
+            explored: HashMap::from([(initial, 0.0)]),
+            goal_test,
+            successors,
+            heuristic,
+            generated: VecDeque::new(),
+            solution: None,
+            done: false,
+        }
+    }
+
+    // Returns None once the goal was found or the frontier is empty
+    pub fn step(&mut self) -> Option<SearchEvent<T>> {
+        if let Some(child) = self.generated.pop_front() {
+            return Some(SearchEvent::Generated(child));
+        }
+        if self.done {
+            return None;
+        }
+        let Some(current_node) = self.frontier.pop() else {
+            self.done = true;
+            return None;
+        };
+        let current_state = current_node.state;
+        if (self.goal_test)(&current_state) {
+            self.done = true;
+            let path = node_to_path(&current_node);
+            self.solution = Some(current_node);
+            return Some(SearchEvent::Found(path));
+        }
+        for child in (self.successors)(&current_state) {
+            let new_cost = current_node.cost + 1.0;
+            if self
+                .explored
+                .get(&child)
+                .is_none_or(|cost| *cost > new_cost)
+            {
+                self.explored.insert(child, new_cost);
+                self.frontier.push(Rc::new(Node::new_with_cost(
+                    child,
+                    Some(Rc::clone(&current_node)),
+                    new_cost,
+                    (self.heuristic)(&child),
+                )));
+                self.generated.push_back(child);
+            }
+        }
+        Some(SearchEvent::Expanded(current_state))
+    }
+
+    // Steps until the search is done and returns the goal node, if any
+    pub fn run(&mut self) -> Option<Rc<Node<T>>> {
+        while self.step().is_some() {}
+        self.solution.clone()
+    }
+
+    // The states in the frontier, in no particular order
+    pub fn frontier(&self) -> Vec<T> {
+        self.frontier.iter().map(|node| node.state).collect()
+    }
+    pub fn solution(&self) -> Option<&Rc<Node<T>>> {
+        self.solution.as_ref()
+    }
+    pub fn is_done(&self) -> bool {
+        self.done && self.generated.is_empty()
+    }
+}
+
+impl<T, G, S, H> Iterator for AstarSearch<T, G, S, H>
+where
+    T: PartialOrd + Copy + Eq + Hash,
+    G: Fn(&T) -> bool,
+    S: Fn(&T) -> Vec<T>,
+    H: Fn(&T) -> f64,
+{
+    type Item = SearchEvent<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // states are numbers, the successors of n are 2n and 2n + 1 up to 15
+    fn tree_successors(state: &u32) -> Vec<u32> {
+        [2 * state, 2 * state + 1]
+            .into_iter()
+            .filter(|child| *child <= 15)
+            .collect()
+    }
+
+    #[test]
+    fn bfs_stepper_reports_events_in_order() {
+        let mut search = UninformedSearch::bfs(1, |state| *state == 5, tree_successors);
+        assert_eq!(search.step(), Some(SearchEvent::Expanded(1)));
+        assert_eq!(search.frontier(), vec![2, 3]);
+        assert_eq!(search.step(), Some(SearchEvent::Generated(2)));
+        assert_eq!(search.step(), Some(SearchEvent::Generated(3)));
+        let rest: Vec<SearchEvent<u32>> = search.by_ref().collect();
+        assert_eq!(
+            rest,
+            vec![
+                SearchEvent::Expanded(2),
+                SearchEvent::Generated(4),
+                SearchEvent::Generated(5),
+                SearchEvent::Expanded(3),
+                SearchEvent::Generated(6),
+                SearchEvent::Generated(7),
+                SearchEvent::Expanded(4),
+                SearchEvent::Generated(8),
+                SearchEvent::Generated(9),
+                SearchEvent::Found(vec![1, 2, 5]),
+            ]
+        );
+        assert!(search.is_done());
+        assert_eq!(search.step(), None);
+        assert_eq!(node_to_path(search.solution().unwrap()), vec![1, 2, 5]);
+    }
+
+    #[test]
+    fn steppers_can_be_interleaved() {
+        let mut dfs = UninformedSearch::dfs(1, |state| *state == 8, tree_successors);
+        let mut astar = AstarSearch::new(1, |state| *state == 8, tree_successors, |_| 0.0);
+        let mut expanded_by_dfs = Vec::new();
+        while !dfs.is_done() || !astar.is_done() {
+            if let Some(SearchEvent::Expanded(state)) = dfs.step() {
+                expanded_by_dfs.push(state);
+            }
+            astar.step();
+        }
+        // the stack pops the last generated child first
+        assert_eq!(
+            expanded_by_dfs,
+            vec![1, 3, 7, 15, 14, 6, 13, 12, 2, 5, 11, 10, 4, 9]
+        );
+        assert_eq!(node_to_path(&dfs.run().unwrap()), vec![1, 2, 4, 8]);
+        assert_eq!(node_to_path(&astar.run().unwrap()), vec![1, 2, 4, 8]);
+        assert!(UninformedSearch::bfs(1, |_| false, tree_successors)
+            .run()
+            .is_none());
+    }
+}
@@ -13,44 +13,10 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use dna::index::IndexedSequence;
+use dna::nucleotide::{parse_gene, parse_sequence, Codon, Gene, Nucleotide};
+use dna::pattern::{find_exact, find_with_mismatches, ExactAlgorithm};
 use std::cmp::Ordering;
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
-enum Nucleotide {
-    A,
-    C,
-    G,
-    T,
-}
-
-type Codon = (Nucleotide, Nucleotide, Nucleotide);
-type Gene = Vec<Codon>;
-
-// Is there a more elegant / idiomatic way for the following?
-fn byte_to_nucleotide(byte: &u8) -> Nucleotide {
-    match byte {
-        b'A' => Nucleotide::A,
-        b'C' => Nucleotide::C,
-        b'G' => Nucleotide::G,
-        b'T' => Nucleotide::T,
-        _ => panic!("Unknown nucleotide!"),
-    }
-}
-
-fn string_to_gene(gene_string: &str) -> Gene {
-    let mut gene = Gene::new();
-    let gene_bytes = gene_string.as_bytes();
-    for index in (0..gene_bytes.len()).step_by(3) {
-        if index + 2 < gene_bytes.len() {
-            let codon = (
-                byte_to_nucleotide(&gene_bytes[index]),
-                byte_to_nucleotide(&gene_bytes[index + 1]),
-                byte_to_nucleotide(&gene_bytes[index + 2]),
-            );
-            gene.push(codon);
-        }
-    }
-    gene
-}
 
 fn linear_contains(gene: &Gene, key_codon: &Codon) -> bool {
     // The following using the built-in Iterator method any would work as well, of course:
@@ -85,7 +51,13 @@ fn binary_contains(gene: &Gene, key_codon: &Codon) -> bool {
 
 fn main() {
     let gene_str = "ACGTGGCTCTCTAACGTACGTACGTACGGGGTTTATATATACCCTAGGACTCCCTTT";
-    let mut gene = string_to_gene(gene_str);
+    let mut gene = match parse_gene(gene_str) {
+        Ok(gene) => gene,
+        Err(error) => {
+            println!("Invalid gene: {error}");
+            return;
+        }
+    };
     let acg = (Nucleotide::A, Nucleotide::C, Nucleotide::G);
     let gat = (Nucleotide::G, Nucleotide::A, Nucleotide::T);
     println!("{}", linear_contains(&gene, &acg));
@@ -94,6 +66,25 @@ fn main() {
     // println!("{}", gene.contains(&acg));
     // println!("{}", gene.contains(&gat));
 
+    // Unlike the searches above, these report where the pattern occurs, on both strands
+    if let (Ok(bases), Ok(pattern)) = (parse_sequence(gene_str), parse_sequence("ACGTA")) {
+        for hit in find_exact(&bases, &pattern, ExactAlgorithm::KnuthMorrisPratt) {
+            println!(
+                "ACGTA at {}..{} on {:?} strand",
+                hit.start, hit.end, hit.strand
+            );
+        }
+        let hits = find_with_mismatches(&bases, &pattern, 1);
+        println!("{} occurrences with at most one mismatch", hits.len());
+        // For many queries on a long sequence, index it once
+        let index = IndexedSequence::new(bases, 3);
+        println!("ACG occurs {} times", index.kmer_frequency(&pattern[..3]));
+        if let Some(repeat) = index.longest_repeated_substring() {
+            let repeat: String = repeat.iter().map(|base| base.to_char()).collect();
+            println!("Longest repeated substring: {repeat}");
+        }
+    }
+
     gene.sort();
     println!("{}", binary_contains(&gene, &acg));
     println!("{}", binary_contains(&gene, &gat));
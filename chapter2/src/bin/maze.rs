@@ -14,6 +14,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 use rand::Rng;
+use std::collections::HashSet;
 
 #[derive(PartialEq)]
 enum Cell {
@@ -184,6 +185,7 @@ fn main() {
         maze.start,
         |ml: &MazeLocation| maze.goal_test(ml),
         |ml: &MazeLocation| maze.successors(ml),
+        HashSet::new(),
     );
     match solution1 {
         None => println!("No solution found using depth-first search"),
@@ -199,6 +201,7 @@ fn main() {
         maze.start,
         |ml: &MazeLocation| maze.goal_test(ml),
         |ml: &MazeLocation| maze.successors(ml),
+        HashSet::new(),
     );
     match solution2 {
         None => println!("No solution found using breadth-first search"),
@@ -13,6 +13,9 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use generic_search::external::{DiskState, ExternalBfsConfig};
+use std::collections::HashSet;
+
 const MAX_NUM: usize = 3;
 
 #[derive(PartialEq, PartialOrd, Eq, Clone, Hash, Copy)]
@@ -150,6 +153,19 @@ impl std::fmt::Display for MCState {
     }
 }
 
+// Allows enumerating the state space with the disk-backed breadth-first search
+impl DiskState for MCState {
+    const SIZE: usize = 3;
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes[0] = self.west_missionaries as u8;
+        bytes[1] = self.west_cannibals as u8;
+        bytes[2] = self.boat_on_west_bank as u8;
+    }
+    fn decode(bytes: &[u8]) -> Self {
+        MCState::new(bytes[0] as usize, bytes[1] as usize, bytes[2] == 1)
+    }
+}
+
 fn display_solution(path: &[MCState]) {
     if path.is_empty() {
         return;
@@ -181,6 +197,7 @@ fn main() {
         start,
         |&state| state.goal_test(),
         |&state| state.successors(),
+        HashSet::new(),
     );
     match solution {
         None => println!("No solution found!"),
@@ -189,4 +206,20 @@ fn main() {
             display_solution(&path);
         }
     }
+
+    // Enumerate all states reachable from the start, one breadth-first layer at a time
+    let config = ExternalBfsConfig::new(std::env::temp_dir());
+    match generic_search::external::external_bfs(
+        start,
+        |_| false,
+        |&state| state.successors(),
+        &config,
+    ) {
+        Err(error) => println!("Disk-backed breadth-first search failed: {error}"),
+        Ok(result) => println!(
+            "{} states are reachable, by depth: {:?}",
+            result.states_visited(),
+            result.layer_sizes
+        ),
+    }
 }
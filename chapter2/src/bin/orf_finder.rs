@@ -0,0 +1,70 @@
+// orf_finder.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 2
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Usage: cargo run --bin orf_finder [FASTA file] [NCBI genetic code number]
+use dna::fasta::{parse_fasta, read_fasta_file, FastaRecord};
+use dna::nucleotide::Strand;
+use dna::translation::{find_orfs, GeneticCode, OrfOptions};
+use dna::DnaError;
+
+const SAMPLE: &str = ">sample two short open reading frames, one on each strand
+ccATGAAACCCGGGTTTAAACCCGGGTTTtaaGG
+cttaAAACCCGGGTTTAAACCCGGGcatcc
+";
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    let records: Result<Vec<FastaRecord>, DnaError> = match arguments.get(1) {
+        Some(file_name) => read_fasta_file(file_name),
+        None => parse_fasta(SAMPLE),
+    };
+    let code = match arguments.get(2) {
+        None => Some(GeneticCode::standard()),
+        Some(id) => id.parse().ok().and_then(GeneticCode::by_id),
+    };
+    let Some(code) = code else {
+        println!("Unknown genetic code {}", arguments[2]);
+        return;
+    };
+    let records = match records {
+        Ok(records) => records,
+        Err(error) => {
+            println!("Could not read the sequences: {error}");
+            return;
+        }
+    };
+    let options = OrfOptions {
+        min_protein_length: 5,
+        alternative_starts: false,
+    };
+    println!("Using the {} genetic code", code.name);
+    for record in records {
+        println!("{} ({} bases)", record.id, record.sequence.len());
+        for orf in find_orfs(&record.sequence, code, &options) {
+            let strand = match orf.frame.strand {
+                Strand::Forward => '+',
+                Strand::Reverse => '-',
+            };
+            println!(
+                "  {strand}{} {}..{}: {}",
+                orf.frame.offset + 1,
+                orf.start,
+                orf.end,
+                orf.protein
+            );
+        }
+    }
+}
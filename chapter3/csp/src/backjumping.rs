@@ -0,0 +1,166 @@
+// csp/src/backjumping.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Conflict-directed backjumping (CBJ). Every failure is explained by a conflict set:
+// the assigned variables of the violated constraints and nogoods. When all values of a
+// variable have failed, the search jumps back to the latest variable in its conflict set,
+// skipping the variables in between, whose values had nothing to do with the failure.
+// The assignments of the conflict set are recorded as a nogood, a combination that is part
+// of no solution, and never tried again, also in later searches with the same Backjumping.
+// Domains are not pruned (the propagation setting of the CSP is ignored).
+use crate::state::{BitSet, SearchState};
+use crate::{Constraint, CSP};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+pub struct Backjumping<'a, V: Eq + Hash, D: Clone, C: Constraint<V, D>> {
+    csp: &'a CSP<V, D, C>,
+    // (variable, value) pairs, the variables in declaration order
+    nogoods: Vec<Vec<(usize, usize)>>,
+    // indices into nogoods of the nogoods each variable is part of
+    nogoods_by_variable: Vec<Vec<usize>>,
+    backjumps: usize,
+}
+
+impl<'a, V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> Backjumping<'a, V, D, C> {
+    pub fn new(csp: &'a CSP<V, D, C>) -> Self {
+        Backjumping {
+            csp,
+            nogoods: Vec::new(),
+            nogoods_by_variable: vec![Vec::new(); csp.variables.len()],
+            backjumps: 0,
+        }
+    }
+
+    pub fn nogoods(&self) -> Vec<Vec<(Rc<V>, D)>> {
+        self.nogoods
+            .iter()
+            .map(|nogood| {
+                nogood
+                    .iter()
+                    .map(|&(variable, value)| {
+                        (
+                            Rc::clone(&self.csp.variables[variable]),
+                            self.csp.values[variable][value].clone(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // How often the search skipped the remaining values of a variable
+    pub fn backjumps(&self) -> usize {
+        self.backjumps
+    }
+
+    pub fn search(&mut self) -> Option<HashMap<Rc<V>, D>> {
+        // the empty nogood: an earlier search found that there is no solution
+        if self.nogoods.iter().any(Vec::is_empty) {
+            return None;
+        }
+        let mut state = SearchState::new(self.csp);
+        match self.internal_search(&mut state) {
+            Ok(()) => Some(state.assignment),
+            Err(_) => None,
+        }
+    }
+
+    // Either completes the assignment or fails with the conflict set of the failure
+    fn internal_search(&mut self, state: &mut SearchState<V, D>) -> Result<(), BitSet> {
+        let csp = self.csp;
+        let Some(variable) = csp.select_unassigned_variable(state, false) else {
+            return Ok(());
+        };
+        let mut conflict_set = BitSet::empty(csp.variables.len());
+        let mut values = Vec::new();
+        csp.order_values(variable, state, &mut values);
+        for value in values {
+            state.assign(csp, variable, value);
+            if !self.add_violations(variable, state, &mut conflict_set) {
+                match self.internal_search(state) {
+                    Ok(()) => return Ok(()),
+                    Err(deeper_conflict_set) => {
+                        if !deeper_conflict_set.contains(variable) {
+                            // the value of variable is not to blame: jump back further
+                            state.unassign(csp, variable);
+                            self.backjumps += 1;
+                            return Err(deeper_conflict_set);
+                        }
+                        for culprit in deeper_conflict_set.iter() {
+                            if culprit != variable {
+                                conflict_set.insert(culprit);
+                            }
+                        }
+                    }
+                }
+            }
+            state.unassign(csp, variable);
+        }
+        self.learn(&conflict_set, state);
+        Err(conflict_set)
+    }
+
+    // Adds the other assigned variables of the first violated nogood or constraint
+    // to the conflict set; returns false if there is none
+    fn add_violations(
+        &self,
+        variable: usize,
+        state: &SearchState<V, D>,
+        conflict_set: &mut BitSet,
+    ) -> bool {
+        for &index in &self.nogoods_by_variable[variable] {
+            let nogood = &self.nogoods[index];
+            if nogood
+                .iter()
+                .all(|&(other, value)| state.values[other] == Some(value))
+            {
+                for &(other, _) in nogood {
+                    if other != variable {
+                        conflict_set.insert(other);
+                    }
+                }
+                return true;
+            }
+        }
+        for &constraint in &self.csp.constraints_of[variable] {
+            if !self.csp.constraints[constraint].satisfied(&state.assignment) {
+                for &other in &self.csp.scopes[constraint] {
+                    if other != variable && state.is_assigned(other) {
+                        conflict_set.insert(other);
+                    }
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    fn learn(&mut self, conflict_set: &BitSet, state: &SearchState<V, D>) {
+        let nogood: Vec<(usize, usize)> = conflict_set
+            .iter()
+            .map(|variable| (variable, state.values[variable].unwrap()))
+            .collect();
+        if self.nogoods.contains(&nogood) {
+            return;
+        }
+        for &(variable, _) in &nogood {
+            self.nogoods_by_variable[variable].push(self.nogoods.len());
+        }
+        self.nogoods.push(nogood);
+    }
+}
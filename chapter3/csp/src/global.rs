@@ -0,0 +1,471 @@
+// csp/src/global.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Global constraints: generic constraints over any number of variables that prune the
+// domains with an algorithm of their own instead of trying value by value.
+// Mixed in one CSP as DynConstraint, e.g. AllDifferent and Linear for SEND+MORE=MONEY.
+use crate::{Constraint, Domains};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+// All variables have different values. Pruning removes every value that is part of no
+// maximum matching between the variables and their values (Régin's algorithm), e.g. with
+// a and b both in {1, 2}, c in {1, 2, 3} loses 1 and 2.
+pub struct AllDifferent<V> {
+    variables: Vec<V>,
+}
+
+impl<V> AllDifferent<V> {
+    pub fn new(variables: Vec<V>) -> Self {
+        AllDifferent { variables }
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Eq + Hash + Clone> Constraint<V, D> for AllDifferent<V> {
+    fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool {
+        let values: Vec<&D> = self
+            .variables
+            .iter()
+            .filter_map(|variable| assignment.get(variable))
+            .collect();
+        (1..values.len()).all(|i| !values[..i].contains(&values[i]))
+    }
+
+    fn variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn prune(&self, domains: &mut Domains<'_, V, D>) -> bool {
+        // the bipartite graph: variable i is connected to the indices of its values
+        let mut value_indices: HashMap<D, usize> = HashMap::new();
+        let edges: Vec<Vec<usize>> = self
+            .variables
+            .iter()
+            .map(|variable| {
+                domains
+                    .values(variable)
+                    .map(|value| {
+                        let next_index = value_indices.len();
+                        *value_indices.entry(value.clone()).or_insert(next_index)
+                    })
+                    .collect()
+            })
+            .collect();
+        let variables = edges.len();
+        let mut variable_of_value = vec![None; value_indices.len()];
+        for variable in 0..variables {
+            let mut visited = vec![false; value_indices.len()];
+            if !augment(variable, &edges, &mut visited, &mut variable_of_value) {
+                // fewer values than variables
+                return false;
+            }
+        }
+        // the matching from the variables to the values, every other edge the other way:
+        // nodes 0..variables are the variables, the values follow
+        let mut graph = vec![Vec::new(); variables + value_indices.len()];
+        for (variable, values) in edges.iter().enumerate() {
+            for &value in values {
+                if variable_of_value[value] == Some(variable) {
+                    graph[variable].push(variables + value);
+                } else {
+                    graph[variables + value].push(variable);
+                }
+            }
+        }
+        // an edge outside the matching belongs to another maximum matching if it lies on
+        // an alternating cycle (its ends are in the same strongly connected component)
+        // or on an alternating path from a value outside the matching
+        let components = strongly_connected_components(&graph);
+        let mut reachable = vec![false; graph.len()];
+        let mut stack: Vec<usize> = (0..value_indices.len())
+            .filter(|&value| variable_of_value[value].is_none())
+            .map(|value| variables + value)
+            .collect();
+        while let Some(node) = stack.pop() {
+            if !reachable[node] {
+                reachable[node] = true;
+                stack.extend(graph[node].iter().copied());
+            }
+        }
+        for (index, variable) in self.variables.iter().enumerate() {
+            let keep = |value: &D| {
+                let value = value_indices[value];
+                variable_of_value[value] == Some(index)
+                    || components[variables + value] == components[index]
+                    || reachable[variables + value]
+            };
+            if !domains.retain(variable, keep) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Kuhn's augmenting path search: matches variable, rematching others if need be
+fn augment(
+    variable: usize,
+    edges: &[Vec<usize>],
+    visited: &mut [bool],
+    variable_of_value: &mut [Option<usize>],
+) -> bool {
+    for &value in &edges[variable] {
+        if visited[value] {
+            continue;
+        }
+        visited[value] = true;
+        if variable_of_value[value]
+            .is_none_or(|other| augment(other, edges, visited, variable_of_value))
+        {
+            variable_of_value[value] = Some(variable);
+            return true;
+        }
+    }
+    false
+}
+
+// Tarjan's algorithm: the number of the strongly connected component of each node
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<usize> {
+    struct Tarjan<'a> {
+        graph: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        component: Vec<usize>,
+        next_index: usize,
+        components: usize,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.next_index);
+            self.low_link[node] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+            for &successor in &self.graph[node] {
+                match self.index[successor] {
+                    None => {
+                        self.visit(successor);
+                        self.low_link[node] = self.low_link[node].min(self.low_link[successor]);
+                    }
+                    Some(index) if self.on_stack[successor] => {
+                        self.low_link[node] = self.low_link[node].min(index);
+                    }
+                    Some(_) => {}
+                }
+            }
+            if Some(self.low_link[node]) == self.index[node] {
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    self.component[member] = self.components;
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components += 1;
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph,
+        index: vec![None; graph.len()],
+        low_link: vec![0; graph.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; graph.len()],
+        component: vec![0; graph.len()],
+        next_index: 0,
+        components: 0,
+    };
+    for node in 0..graph.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.component
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+// The sum of coefficient * variable compared with a constant, over integer values.
+// Pruning keeps the values that fit the bounds of the other terms, until nothing changes.
+pub struct Linear<V> {
+    terms: Vec<(i64, V)>,
+    comparison: Comparison,
+    constant: i64,
+    variables: Vec<V>,
+}
+
+impl<V: Clone> Linear<V> {
+    pub fn new(terms: Vec<(i64, V)>, comparison: Comparison, constant: i64) -> Self {
+        let variables = terms.iter().map(|(_, variable)| variable.clone()).collect();
+        Linear {
+            terms,
+            comparison,
+            constant,
+            variables,
+        }
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Copy + Into<i64>> Constraint<V, D> for Linear<V> {
+    fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool {
+        let values: Option<Vec<i64>> = self
+            .terms
+            .iter()
+            .map(|(coefficient, variable)| {
+                assignment
+                    .get(variable)
+                    .map(|&value| coefficient * value.into())
+            })
+            .collect();
+        // a partial assignment is left to prune
+        let Some(values) = values else {
+            return true;
+        };
+        let sum: i64 = values.iter().sum();
+        match self.comparison {
+            Comparison::Equal => sum == self.constant,
+            Comparison::LessOrEqual => sum <= self.constant,
+            Comparison::GreaterOrEqual => sum >= self.constant,
+        }
+    }
+
+    fn variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn prune(&self, domains: &mut Domains<'_, V, D>) -> bool {
+        loop {
+            // the smallest and largest value of each term
+            let mut bounds = Vec::with_capacity(self.terms.len());
+            for (coefficient, variable) in &self.terms {
+                let products = domains
+                    .values(variable)
+                    .map(|&value| coefficient * value.into());
+                let (min, max) = products.fold((i64::MAX, i64::MIN), |(min, max), product| {
+                    (min.min(product), max.max(product))
+                });
+                if min > max {
+                    return false;
+                }
+                bounds.push((min, max));
+            }
+            let min_sum: i64 = bounds.iter().map(|(min, _)| min).sum();
+            let max_sum: i64 = bounds.iter().map(|(_, max)| max).sum();
+            let mut changed = false;
+            for ((coefficient, variable), (min, max)) in self.terms.iter().zip(bounds) {
+                // the range of the term leaving the comparison possible for the other terms
+                let (lowest, highest) = match self.comparison {
+                    Comparison::Equal => (
+                        self.constant - (max_sum - max),
+                        self.constant - (min_sum - min),
+                    ),
+                    Comparison::LessOrEqual => (i64::MIN, self.constant - (min_sum - min)),
+                    Comparison::GreaterOrEqual => (self.constant - (max_sum - max), i64::MAX),
+                };
+                if min >= lowest && max <= highest {
+                    continue;
+                }
+                let before = domains.len(variable);
+                if !domains.retain(variable, |&value| {
+                    (lowest..=highest).contains(&(coefficient * value.into()))
+                }) {
+                    return false;
+                }
+                changed |= domains.len(variable) < before;
+            }
+            if !changed {
+                return true;
+            }
+        }
+    }
+}
+
+// The values of the variables are one of the allowed tuples. Pruning keeps the values
+// that occur in a tuple whose other values are all still possible.
+pub struct Table<V, D> {
+    variables: Vec<V>,
+    tuples: Vec<Vec<D>>,
+}
+
+impl<V, D> Table<V, D> {
+    // Every tuple holds a value for each of the variables, in the same order
+    pub fn new(variables: Vec<V>, tuples: Vec<Vec<D>>) -> Self {
+        Table { variables, tuples }
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: PartialEq + Clone> Constraint<V, D> for Table<V, D> {
+    fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool {
+        self.tuples.iter().any(|tuple| {
+            self.variables
+                .iter()
+                .zip(tuple)
+                .all(|(variable, value)| assignment.get(variable).is_none_or(|v| v == value))
+        })
+    }
+
+    fn variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn prune(&self, domains: &mut Domains<'_, V, D>) -> bool {
+        let supported: Vec<&Vec<D>> = self
+            .tuples
+            .iter()
+            .filter(|tuple| {
+                self.variables
+                    .iter()
+                    .zip(tuple.iter())
+                    .all(|(variable, value)| domains.values(variable).any(|v| v == value))
+            })
+            .collect();
+        for (position, variable) in self.variables.iter().enumerate() {
+            if !domains.retain(variable, |value| {
+                supported.iter().any(|tuple| &tuple[position] == value)
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// value = array[index], with index counting from 0. Pruning keeps the indices whose
+// element is still possible for value, and the values that are elements at such an index.
+pub struct Element<V, D> {
+    array: Vec<D>,
+    variables: Vec<V>,
+}
+
+impl<V, D> Element<V, D> {
+    pub fn new(index: V, array: Vec<D>, value: V) -> Self {
+        Element {
+            array,
+            variables: vec![index, value],
+        }
+    }
+
+    fn element(&self, index: i64) -> Option<&D> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.array.get(index))
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Copy + Into<i64> + PartialEq> Constraint<V, D> for Element<V, D> {
+    fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool {
+        let [index, value] = [&self.variables[0], &self.variables[1]];
+        match (assignment.get(index), assignment.get(value)) {
+            (Some(&index), Some(value)) => self.element(index.into()) == Some(value),
+            (Some(&index), None) => self.element(index.into()).is_some(),
+            _ => true,
+        }
+    }
+
+    fn variables(&self) -> &Vec<V> {
+        &self.variables
+    }
+
+    fn prune(&self, domains: &mut Domains<'_, V, D>) -> bool {
+        let [index, value] = [&self.variables[0], &self.variables[1]];
+        let values: Vec<D> = domains.values(value).copied().collect();
+        if !domains.retain(index, |&index| {
+            self.element(index.into())
+                .is_some_and(|element| values.contains(element))
+        }) {
+            return false;
+        }
+        let elements: Vec<D> = domains
+            .values(index)
+            .filter_map(|&index| self.element(index.into()).copied())
+            .collect();
+        domains.retain(value, |value| elements.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{BitSet, SearchState};
+    use crate::{DynConstraint, CSP};
+
+    // The domains left after pruning once with each constraint, in declaration order
+    fn pruned<D: Clone, C: Constraint<char, D>>(csp: &CSP<char, D, C>) -> Option<Vec<Vec<D>>> {
+        let mut state = SearchState::new(csp);
+        let mut changed = BitSet::empty(csp.variables.len());
+        for constraint in &csp.constraints {
+            if !constraint.prune(&mut Domains::new(csp, &mut state, &mut changed)) {
+                return None;
+            }
+        }
+        Some(
+            (0..csp.variables.len())
+                .map(|variable| {
+                    state.domains[variable]
+                        .iter()
+                        .map(|value| csp.values[variable][value].clone())
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn global_constraints_prune() {
+        let mut csp = CSP::new([('a', vec![1, 2]), ('b', vec![1, 2]), ('c', vec![1, 2, 3])]);
+        csp.add_constraint(AllDifferent::new(vec!['a', 'b', 'c']));
+        assert_eq!(pruned(&csp), Some(vec![vec![1, 2], vec![1, 2], vec![3]]));
+        let mut csp = CSP::new([('a', vec![1, 2]), ('b', vec![1, 2]), ('c', vec![1, 2])]);
+        csp.add_constraint(AllDifferent::new(vec!['a', 'b', 'c']));
+        assert_eq!(pruned(&csp), None);
+
+        let mut csp = CSP::new([('x', vec![0u8, 1, 2, 3]), ('y', vec![0, 1, 2, 3])]);
+        csp.add_constraint(Linear::new(vec![(1, 'x'), (2, 'y')], Comparison::Equal, 7));
+        assert_eq!(pruned(&csp), Some(vec![vec![1, 2, 3], vec![2, 3]]));
+        let mut csp = CSP::new([('x', vec![0u8, 1, 2, 3]), ('y', vec![0, 1, 2, 3])]);
+        csp.add_constraint(Linear::new(
+            vec![(1, 'x'), (-1, 'y')],
+            Comparison::GreaterOrEqual,
+            2,
+        ));
+        assert_eq!(pruned(&csp), Some(vec![vec![2, 3], vec![0, 1]]));
+
+        let mut csp = CSP::<char, u8, DynConstraint<char, u8>>::new([
+            ('i', vec![0, 1, 2, 3, 4]),
+            ('v', vec![10, 20, 30]),
+            ('w', vec![1, 2, 3]),
+        ]);
+        csp.add_constraint(Box::new(Element::new('i', vec![30, 40, 10, 10], 'v')));
+        csp.add_constraint(Box::new(Table::new(
+            vec!['v', 'w'],
+            vec![vec![10, 1], vec![20, 2], vec![40, 3]],
+        )));
+        assert_eq!(pruned(&csp), Some(vec![vec![0, 2, 3], vec![10], vec![1]]));
+        let solutions: Vec<u8> = csp.solutions().map(|solution| solution[&'i']).collect();
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions.contains(&2) && solutions.contains(&3));
+    }
+}
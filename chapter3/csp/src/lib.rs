@@ -14,10 +14,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::Hash;
 use std::rc::Rc;
 
+mod backjumping;
+mod global;
+mod local_search;
+mod optimisation;
+mod search;
+mod state;
+
+pub use backjumping::Backjumping;
+pub use global::{AllDifferent, Comparison, Element, Linear, Table};
+pub use local_search::{MinConflictsOptions, MinConflictsResult, ViolationCounter};
+pub use optimisation::{Bounded, Improvements, Objective, SoftConstraints};
+pub use search::Solutions;
+pub use state::Domains;
+
 // Rust doesn't have abstract classes or method overloading like usual OO languages
 // But structures and traits, where traits cannot hold any data
 // --> Holding a constraint's data must be in a struct and the "abstract" methods
@@ -30,95 +46,986 @@ use std::rc::Rc;
 //
 // At least for the examples in the book, it seems to be sufficient if all
 // Constraints held by the CSP are of the same "subtype" --> parameterize the trait
+//
+// Because V and D are parameters of the trait and not of its methods, Constraint<V, D> can be
+// made into an object after all: with C = DynConstraint<V, D>, a CSP holds boxed constraints
+// of different types, e.g. an all-different constraint together with a sum constraint
 
 pub trait Constraint<V, D> {
     fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool;
     fn variables(&self) -> &Vec<V>;
+    // Removes values of its variables that cannot be part of a solution, going beyond
+    // checking satisfied value by value; returns false if there can be no solution.
+    // Called during propagation (Propagation::None turns it off) whenever a domain of
+    // one of its variables has changed; the default does not prune anything.
+    fn prune(&self, _domains: &mut Domains<'_, V, D>) -> bool {
+        true
+    }
+    // For min-conflicts: counts the violations of a constraint over many variables one by
+    // one (see ViolationCounter) instead of a violated constraint as one; the default has none.
+    fn violation_counter(&self) -> Option<Box<dyn ViolationCounter<V, D> + '_>> {
+        None
+    }
+}
+
+pub type DynConstraint<V, D> = Box<dyn Constraint<V, D>>;
+
+// A boxed constraint is a constraint, so that Box<dyn Constraint<V, D>> can be used as C
+impl<V, D, C: Constraint<V, D> + ?Sized> Constraint<V, D> for Box<C> {
+    fn satisfied(&self, assignment: &HashMap<Rc<V>, D>) -> bool {
+        (**self).satisfied(assignment)
+    }
+    fn variables(&self) -> &Vec<V> {
+        (**self).variables()
+    }
+    fn prune(&self, domains: &mut Domains<'_, V, D>) -> bool {
+        (**self).prune(domains)
+    }
+    fn violation_counter(&self) -> Option<Box<dyn ViolationCounter<V, D> + '_>> {
+        (**self).violation_counter()
+    }
+}
+
+// Mistakes in a model, found by CSPBuilder before any search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CSPError<V> {
+    // A constraint refers to a variable without a domain
+    UnknownVariable(V),
+    // A variable has no values at all, so there can be no solution
+    EmptyDomain(V),
+    // A variable was given more than once
+    DuplicateVariable(V),
+    // The constraint added as number index (counting from 0) has no variables,
+    // so the solver would never check it
+    ConstraintWithoutVariables { index: usize },
+}
+
+impl<V: fmt::Debug> fmt::Display for CSPError<V> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CSPError::UnknownVariable(variable) => {
+                write!(formatter, "variable {variable:?} in constraint not in CSP")
+            }
+            CSPError::EmptyDomain(variable) => {
+                write!(formatter, "variable {variable:?} has an empty domain")
+            }
+            CSPError::DuplicateVariable(variable) => {
+                write!(
+                    formatter,
+                    "variable {variable:?} is declared more than once"
+                )
+            }
+            CSPError::ConstraintWithoutVariables { index } => {
+                write!(formatter, "constraint {index} has no variables")
+            }
+        }
+    }
+}
+
+impl<V: fmt::Debug> std::error::Error for CSPError<V> {}
+
+// Everything CSPBuilder::validate found, in the order of the declarations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport<V> {
+    pub errors: Vec<CSPError<V>>,
+    // Variables in no constraint: not an error, but often a forgotten constraint
+    pub unconstrained: Vec<V>,
+    // Number of combinations of values, saturating at usize::MAX
+    pub search_space: usize,
+}
+
+impl<V> ValidationReport<V> {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl<V: fmt::Debug> fmt::Display for ValidationReport<V> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for error in &self.errors {
+            writeln!(formatter, "error: {error}")?;
+        }
+        if !self.unconstrained.is_empty() {
+            writeln!(
+                formatter,
+                "warning: variables in no constraint: {:?}",
+                self.unconstrained
+            )?;
+        }
+        write!(formatter, "search space: {} assignments", self.search_space)
+    }
+}
+
+// How the solver picks the next variable to assign
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariableOrdering {
+    // The first unassigned variable in the order the variables were given to CSP::new
+    Declaration,
+    // The variable with the fewest values left that are consistent with the assignment,
+    // ties broken by degree: fails early on dead ends
+    #[default]
+    MinimumRemainingValues,
+    // The variable sharing constraints with the most unassigned variables
+    Degree,
+}
+
+// In which order the solver tries the values of a variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValueOrdering {
+    // The order of the domain
+    #[default]
+    Domain,
+    // The value that rules out the fewest values of the unassigned neighbours first
+    LeastConstrainingValue,
+}
+
+// How the domains of the unassigned variables are pruned after each assignment.
+// Pruning relies on constraints being false only for partial assignments that cannot
+// be completed, which is what Constraint::satisfied is expected to do anyway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Propagation {
+    // Only check the constraints of the variable just assigned
+    None,
+    // Remove the values of the neighbours of the variable just assigned that are no longer
+    // consistent; an empty domain means backtracking at once
+    #[default]
+    ForwardChecking,
+    // Forward checking followed by AC-3 on the pairs of unassigned neighbours
+    // (maintaining arc consistency, MAC): every value left has a consistent value in each
+    // neighbour's domain. Also applied once before the search starts.
+    ArcConsistency,
 }
 
 pub struct CSP<V: Eq + Hash, D: Clone, C: Constraint<V, D> + Sized> {
-    // Because each variable must have a domain, we can use the keys of the domains HashMap as "variables"
-    //   to avoid having to copy each variable into an explicit vector of variables
-    // variables: Vec<V>,
-    // The variables used as keys in domains and constraints are the same, and the same constraint is
-    //   frequently shared among several variables,
+    // The search refers to variables, values and constraints by their indices in these vectors:
+    // the variables in the order they were declared, so that the search is deterministic,
+    // the domain values of each variable, and the constraints in the order they were added
+    // The variables used as keys in the assignments are shared with the indices,
     //   so use Reference Counting to avoid copying
-    domains: HashMap<Rc<V>, Vec<D>>,
-    constraints: HashMap<Rc<V>, Vec<Rc<C>>>,
+    pub(crate) variables: Vec<Rc<V>>,
+    indices: HashMap<Rc<V>, usize>,
+    pub(crate) values: Vec<Vec<D>>,
+    pub(crate) constraints: Vec<C>,
+    // The variables of each constraint
+    pub(crate) scopes: Vec<Vec<usize>>,
+    // The constraints each variable is part of
+    pub(crate) constraints_of: Vec<Vec<usize>>,
+    // The variables sharing at least one constraint with a variable, see neighbours()
+    neighbours: OnceCell<Vec<Vec<usize>>>,
+    pub(crate) variable_ordering: VariableOrdering,
+    pub(crate) value_ordering: ValueOrdering,
+    pub(crate) propagation: Propagation,
 }
 
 impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D> + Sized> CSP<V, D, C> {
-    pub fn new(domains_in: HashMap<V, Vec<D>>) -> Self {
-        let mut constraints = HashMap::<Rc<V>, Vec<Rc<C>>>::new();
-        let mut domains = HashMap::<Rc<V>, Vec<D>>::new();
+    // The variables with their domains; pass a Vec of pairs instead of a HashMap
+    // to decide the declaration order
+    pub fn new(domains_in: impl IntoIterator<Item = (V, Vec<D>)>) -> Self {
+        let mut variables = Vec::<Rc<V>>::new();
+        let mut indices = HashMap::<Rc<V>, usize>::new();
+        let mut values = Vec::<Vec<D>>::new();
         for (variable, domain) in domains_in {
+            // a variable given twice keeps its position, but gets the later domain
+            if let Some(&index) = indices.get(&variable) {
+                values[index] = domain;
+                continue;
+            }
             let variable = Rc::new(variable);
-            domains.insert(Rc::clone(&variable), domain);
-            constraints.insert(Rc::clone(&variable), Vec::<Rc<C>>::new());
+            indices.insert(Rc::clone(&variable), variables.len());
+            variables.push(variable);
+            values.push(domain);
         }
         CSP {
-            domains,
-            constraints,
+            constraints_of: vec![Vec::new(); variables.len()],
+            neighbours: OnceCell::new(),
+            variables,
+            indices,
+            values,
+            constraints: Vec::new(),
+            scopes: Vec::new(),
+            variable_ordering: VariableOrdering::default(),
+            value_ordering: ValueOrdering::default(),
+            propagation: Propagation::default(),
         }
     }
 
+    pub fn set_variable_ordering(&mut self, ordering: VariableOrdering) {
+        self.variable_ordering = ordering;
+    }
+
+    pub fn set_value_ordering(&mut self, ordering: ValueOrdering) {
+        self.value_ordering = ordering;
+    }
+
+    pub fn set_propagation(&mut self, propagation: Propagation) {
+        self.propagation = propagation;
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = &V> {
+        self.variables.iter().map(|variable| variable.as_ref())
+    }
+
     pub fn add_constraint(&mut self, constraint: C) {
-        let constraint = Rc::new(constraint);
+        if self.try_add_constraint(constraint).is_err() {
+            panic!("Variable in constraint not in CSP");
+        }
+    }
+
+    // Like add_constraint, but with an error instead of a panic for an unknown variable
+    pub fn try_add_constraint(&mut self, constraint: C) -> Result<(), CSPError<V>> {
+        if let Some(variable) = constraint
+            .variables()
+            .iter()
+            .find(|&variable| !self.indices.contains_key(variable))
+        {
+            return Err(CSPError::UnknownVariable(variable.clone()));
+        }
+        let index = self.constraints.len();
+        let mut scope = Vec::<usize>::new();
         for variable in constraint.variables() {
-            if !self.domains.contains_key(variable) {
-                panic!("Variable in constraint not in CSP");
-            } else {
-                self.constraints
-                    .get_mut(variable)
-                    .expect("Variable in constraint not in CSP")
-                    .push(Rc::clone(&constraint));
+            let variable = self.indices[variable];
+            // a variable given twice has the constraint added already
+            if self.constraints_of[variable].last() != Some(&index) {
+                self.constraints_of[variable].push(index);
+                scope.push(variable);
             }
         }
+        self.neighbours.take();
+        self.constraints.push(constraint);
+        self.scopes.push(scope);
+        Ok(())
+    }
+
+    // The variables sharing at least one constraint with each variable, in the order of
+    // the constraints. Worked out when a search first needs them, as they take memory
+    // quadratic in the size of a constraint, which min-conflicts does without.
+    pub(crate) fn neighbours(&self) -> &[Vec<usize>] {
+        self.neighbours.get_or_init(|| {
+            let mut neighbours = vec![Vec::new(); self.variables.len()];
+            // the variable each variable was last added to as a neighbour
+            let mut added_to = vec![usize::MAX; self.variables.len()];
+            for (variable, constraints) in self.constraints_of.iter().enumerate() {
+                for &constraint in constraints {
+                    for &other in &self.scopes[constraint] {
+                        if other != variable && added_to[other] != variable {
+                            added_to[other] = variable;
+                            neighbours[variable].push(other);
+                        }
+                    }
+                }
+            }
+            neighbours
+        })
     }
 
     // Check if the value assignment is consistent by checking all constraints
     // for the given variable against it
     pub fn is_consistent(&self, variable: &V, assignment: &HashMap<Rc<V>, D>) -> bool {
-        if let Some(constraints_for_variable) = self.constraints.get(variable) {
-            for constraint in constraints_for_variable {
-                if !constraint.satisfied(assignment) {
-                    return false;
-                }
-            }
+        match self.indices.get(variable) {
+            Some(&index) => self.consistent(index, assignment),
+            None => true,
         }
-        true
     }
 
     pub fn backtracking_search(&self) -> Option<HashMap<Rc<V>, D>> {
-        self.internal_backtracking_search(HashMap::<Rc<V>, D>::new())
+        self.solutions().next()
+    }
+
+    // All solutions, found one after the other as the iterator is advanced
+    pub fn solutions(&self) -> Solutions<'_, V, D, C> {
+        Solutions::new(self)
     }
-    fn internal_backtracking_search(
+
+    // The number of solutions, counting at most limit of them
+    pub fn count_solutions(&self, limit: Option<usize>) -> usize {
+        self.solutions().take(limit.unwrap_or(usize::MAX)).count()
+    }
+
+    // Stops the search at the second solution, e.g. to check a generated puzzle
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(Some(2)) == 1
+    }
+
+    // The first solution found by conflict-directed backjumping, see Backjumping
+    pub fn backjumping_search(&self) -> Option<HashMap<Rc<V>, D>> {
+        Backjumping::new(self).search()
+    }
+
+    // The solutions that are cheaper than all found before, with their costs, found by
+    // branch-and-bound; the last one is optimal
+    pub fn improving_solutions<'a, O: Objective<V, D> + ?Sized>(
+        &'a self,
+        objective: &'a O,
+    ) -> Improvements<'a, V, D, C, O> {
+        Improvements::new(self, objective)
+    }
+
+    // A solution with the lowest cost and that cost, None if there is no solution
+    pub fn minimise<O: Objective<V, D> + ?Sized>(
         &self,
-        assignment: HashMap<Rc<V>, D>,
-    ) -> Option<HashMap<Rc<V>, D>> {
-        // get every possible domain value of the first unassigned variable
-        if let Some(first_unassigned) = self
-            .domains
-            .keys() // get all variables
-            .find(|var| !assignment.contains_key(*var))
-        {
-            if let Some(values) = self.domains.get(first_unassigned) {
-                for value in values {
-                    let mut local_assignment = assignment.clone();
-                    local_assignment.insert(Rc::clone(first_unassigned), (*value).clone());
-                    // if we're still consistent, we recurse (continue)
-                    if self.is_consistent(first_unassigned, &local_assignment) {
-                        let result = self.internal_backtracking_search(local_assignment);
-                        if result.is_some() {
-                            return result;
-                        }
-                    }
+        objective: &O,
+    ) -> Option<(HashMap<Rc<V>, D>, i64)> {
+        self.improving_solutions(objective).last()
+    }
+
+    // The best assignment found by min-conflicts local search, see MinConflictsOptions;
+    // meant for large problems with a solution, as it cannot show that there is none,
+    // and with small constraints or ones that count their violations (see module local_search).
+    // Fails if a variable has an empty domain, as there is no assignment to start from.
+    pub fn min_conflicts(
+        &self,
+        options: &MinConflictsOptions,
+    ) -> Result<MinConflictsResult<V, D>, CSPError<V>> {
+        local_search::min_conflicts(self, options)
+    }
+}
+
+// Fallible construction of a CSP: instead of panicking, build returns the first mistake
+// in the model and validate reports all of them
+pub struct CSPBuilder<V, D, C> {
+    domains: Vec<(V, Vec<D>)>,
+    constraints: Vec<C>,
+    variable_ordering: VariableOrdering,
+    value_ordering: ValueOrdering,
+    propagation: Propagation,
+}
+
+impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> CSPBuilder<V, D, C> {
+    pub fn new() -> Self {
+        CSPBuilder {
+            domains: Vec::new(),
+            constraints: Vec::new(),
+            variable_ordering: VariableOrdering::default(),
+            value_ordering: ValueOrdering::default(),
+            propagation: Propagation::default(),
+        }
+    }
+
+    pub fn variable(mut self, variable: V, domain: Vec<D>) -> Self {
+        self.domains.push((variable, domain));
+        self
+    }
+
+    pub fn variables(mut self, domains: impl IntoIterator<Item = (V, Vec<D>)>) -> Self {
+        self.domains.extend(domains);
+        self
+    }
+
+    pub fn constraint(mut self, constraint: C) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    pub fn variable_ordering(mut self, ordering: VariableOrdering) -> Self {
+        self.variable_ordering = ordering;
+        self
+    }
+
+    pub fn value_ordering(mut self, ordering: ValueOrdering) -> Self {
+        self.value_ordering = ordering;
+        self
+    }
+
+    pub fn propagation(mut self, propagation: Propagation) -> Self {
+        self.propagation = propagation;
+        self
+    }
+
+    pub fn validate(&self) -> ValidationReport<V> {
+        let mut errors = Vec::new();
+        let mut declared = HashSet::<&V>::new();
+        let mut search_space = 1usize;
+        for (variable, domain) in &self.domains {
+            if !declared.insert(variable) {
+                errors.push(CSPError::DuplicateVariable(variable.clone()));
+            } else if domain.is_empty() {
+                errors.push(CSPError::EmptyDomain(variable.clone()));
+            }
+            search_space = search_space.saturating_mul(domain.len());
+        }
+        let mut constrained = HashSet::<&V>::new();
+        for (index, constraint) in self.constraints.iter().enumerate() {
+            if constraint.variables().is_empty() {
+                errors.push(CSPError::ConstraintWithoutVariables { index });
+            }
+            for variable in constraint.variables() {
+                if !declared.contains(variable) {
+                    errors.push(CSPError::UnknownVariable(variable.clone()));
+                }
+                constrained.insert(variable);
+            }
+        }
+        let mut unconstrained = Vec::new();
+        for (variable, _) in &self.domains {
+            if !constrained.contains(variable) && !unconstrained.contains(variable) {
+                unconstrained.push(variable.clone());
+            }
+        }
+        ValidationReport {
+            errors,
+            unconstrained,
+            search_space,
+        }
+    }
+
+    pub fn build(self) -> Result<CSP<V, D, C>, CSPError<V>> {
+        if let Some(error) = self.validate().errors.into_iter().next() {
+            return Err(error);
+        }
+        let mut csp = CSP::new(self.domains);
+        csp.set_variable_ordering(self.variable_ordering);
+        csp.set_value_ordering(self.value_ordering);
+        csp.set_propagation(self.propagation);
+        for constraint in self.constraints {
+            csp.try_add_constraint(constraint)?;
+        }
+        Ok(csp)
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> Default for CSPBuilder<V, D, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum {
+        variables: Vec<char>,
+        total: u32,
+    }
+
+    impl Constraint<char, u32> for Sum {
+        fn satisfied(&self, assignment: &HashMap<Rc<char>, u32>) -> bool {
+            let values: Vec<u32> = self
+                .variables
+                .iter()
+                .filter_map(|variable| assignment.get(variable).copied())
+                .collect();
+            let sum: u32 = values.iter().sum();
+            if values.len() == self.variables.len() {
+                sum == self.total
+            } else {
+                sum <= self.total
+            }
+        }
+        fn variables(&self) -> &Vec<char> {
+            &self.variables
+        }
+    }
+
+    struct Queens {
+        columns: Vec<u8>,
+    }
+
+    impl Constraint<u8, u8> for Queens {
+        fn satisfied(&self, assignment: &HashMap<Rc<u8>, u8>) -> bool {
+            assignment.iter().all(|(column1, row1)| {
+                assignment.iter().all(|(column2, row2)| {
+                    column1 == column2
+                        || (row1 != row2 && column1.abs_diff(**column2) != row1.abs_diff(*row2))
+                })
+            })
+        }
+        fn variables(&self) -> &Vec<u8> {
+            &self.columns
+        }
+    }
+
+    // The two queens do not attack each other
+    struct QueenPair {
+        columns: Vec<u8>,
+    }
+
+    impl Constraint<u8, u8> for QueenPair {
+        fn satisfied(&self, assignment: &HashMap<Rc<u8>, u8>) -> bool {
+            let (column1, column2) = (self.columns[0], self.columns[1]);
+            match (assignment.get(&column1), assignment.get(&column2)) {
+                (Some(row1), Some(row2)) => {
+                    row1 != row2 && column1.abs_diff(column2) != row1.abs_diff(*row2)
                 }
+                _ => true,
+            }
+        }
+        fn variables(&self) -> &Vec<u8> {
+            &self.columns
+        }
+    }
+
+    fn queens(n: u8) -> CSP<u8, u8, Queens> {
+        let columns: Vec<u8> = (1..=n).collect();
+        let mut csp = CSP::new(columns.iter().map(|&column| (column, (1..=n).collect())));
+        csp.add_constraint(Queens { columns });
+        csp
+    }
+
+    // One constraint per pair of queens instead of one for all of them, so that min-conflicts
+    // can tell how many queens attack each other
+    fn queen_pairs(n: u8) -> CSP<u8, u8, QueenPair> {
+        let mut csp = CSP::new((1..=n).map(|column| (column, (1..=n).collect())));
+        for column1 in 1..=n {
+            for column2 in column1 + 1..=n {
+                csp.add_constraint(QueenPair {
+                    columns: vec![column1, column2],
+                });
+            }
+        }
+        csp
+    }
+
+    fn is_queens_solution(n: u8, solution: &HashMap<Rc<u8>, u8>) -> bool {
+        solution.len() == n as usize
+            && Queens {
+                columns: (1..=n).collect(),
             }
-            None
-        } else {
-            // assignment is complete if every variable is assigned (our base case)
-            Some(assignment)
+            .satisfied(solution)
+    }
+
+    // All queens in one constraint, which counts the pairs of queens that attack each other
+    // for min-conflicts; columns and rows count from 1
+    struct AttackingQueens {
+        columns: Vec<u16>,
+    }
+
+    impl Constraint<u16, u16> for AttackingQueens {
+        fn satisfied(&self, assignment: &HashMap<Rc<u16>, u16>) -> bool {
+            let mut counter = QueenCounter::new(self.columns.len());
+            for (column, row) in assignment {
+                counter.assign(column, None, row);
+            }
+            counter.violations() == 0
+        }
+        fn variables(&self) -> &Vec<u16> {
+            &self.columns
+        }
+        fn violation_counter(&self) -> Option<Box<dyn ViolationCounter<u16, u16> + '_>> {
+            Some(Box::new(QueenCounter::new(self.columns.len())))
+        }
+    }
+
+    // The number of queens in each row and diagonal and the row of each column
+    struct QueenCounter {
+        n: usize,
+        rows: Vec<usize>,
+        diagonals: Vec<usize>,
+        anti_diagonals: Vec<usize>,
+        row_of: Vec<u16>,
+        pairs: usize,
+    }
+
+    impl QueenCounter {
+        fn new(n: usize) -> Self {
+            QueenCounter {
+                n,
+                rows: vec![0; n + 1],
+                diagonals: vec![0; 2 * n + 1],
+                anti_diagonals: vec![0; 2 * n + 1],
+                row_of: vec![0; n + 1],
+                pairs: 0,
+            }
+        }
+        fn lines(&self, column: u16, row: u16) -> [usize; 3] {
+            let (column, row) = (column as usize, row as usize);
+            [row, column + row, self.n + column - row]
+        }
+    }
+
+    impl ViolationCounter<u16, u16> for QueenCounter {
+        fn assign(&mut self, &column: &u16, old: Option<&u16>, &new: &u16) {
+            if let Some(&old) = old {
+                let [row, diagonal, anti_diagonal] = self.lines(column, old);
+                for count in [
+                    &mut self.rows[row],
+                    &mut self.diagonals[diagonal],
+                    &mut self.anti_diagonals[anti_diagonal],
+                ] {
+                    *count -= 1;
+                    self.pairs -= *count;
+                }
+            }
+            let [row, diagonal, anti_diagonal] = self.lines(column, new);
+            for count in [
+                &mut self.rows[row],
+                &mut self.diagonals[diagonal],
+                &mut self.anti_diagonals[anti_diagonal],
+            ] {
+                self.pairs += *count;
+                *count += 1;
+            }
+            self.row_of[column as usize] = new;
+        }
+        fn violations(&self) -> usize {
+            self.pairs
+        }
+        fn violations_with(&self, &column: &u16, &row: &u16) -> usize {
+            let [row_line, diagonal, anti_diagonal] = self.lines(column, row);
+            let queens =
+                self.rows[row_line] + self.diagonals[diagonal] + self.anti_diagonals[anti_diagonal];
+            // not attacked by the queen of its own column
+            if self.row_of[column as usize] == row {
+                queens - 3
+            } else {
+                queens
+            }
+        }
+    }
+
+    #[test]
+    fn orderings_are_deterministic() {
+        for variable_ordering in [
+            VariableOrdering::Declaration,
+            VariableOrdering::MinimumRemainingValues,
+            VariableOrdering::Degree,
+        ] {
+            for value_ordering in [ValueOrdering::Domain, ValueOrdering::LeastConstrainingValue] {
+                let mut csp = queens(8);
+                csp.set_variable_ordering(variable_ordering);
+                csp.set_value_ordering(value_ordering);
+                let solution = csp.backtracking_search().unwrap();
+                assert!(is_queens_solution(8, &solution));
+                assert_eq!(csp.backtracking_search(), Some(solution));
+            }
+        }
+        // in declaration order, the first solution found is the lexicographically smallest
+        let mut csp = queens(8);
+        csp.set_variable_ordering(VariableOrdering::Declaration);
+        let solution = csp.backtracking_search().unwrap();
+        let rows: Vec<u8> = (1..=8).map(|column| solution[&column]).collect();
+        assert_eq!(rows, [1, 5, 8, 6, 3, 7, 2, 4]);
+        assert_eq!(
+            csp.variables().copied().collect::<Vec<u8>>(),
+            (1..=8).collect::<Vec<u8>>()
+        );
+        assert!(queens(3).backtracking_search().is_none());
+    }
+
+    #[test]
+    fn propagation_prunes_domains() {
+        for propagation in [
+            Propagation::None,
+            Propagation::ForwardChecking,
+            Propagation::ArcConsistency,
+        ] {
+            let mut csp = queens(8);
+            csp.set_propagation(propagation);
+            csp.set_variable_ordering(VariableOrdering::Declaration);
+            let solution = csp.backtracking_search().unwrap();
+            let rows: Vec<u8> = (1..=8).map(|column| solution[&column]).collect();
+            assert_eq!(rows, [1, 5, 8, 6, 3, 7, 2, 4]);
+            csp.set_variable_ordering(VariableOrdering::MinimumRemainingValues);
+            assert!(is_queens_solution(8, &csp.backtracking_search().unwrap()));
+        }
+        // too slow without propagation
+        let mut csp = queens(30);
+        csp.set_propagation(Propagation::ArcConsistency);
+        assert!(is_queens_solution(30, &csp.backtracking_search().unwrap()));
+        assert!(is_queens_solution(
+            30,
+            &queens(30).backtracking_search().unwrap()
+        ));
+
+        // arc consistency finds the wipeout before the search starts
+        let domains = vec![('a', vec![1]), ('b', vec![1, 2]), ('c', vec![1, 2])];
+        let mut csp = CSP::<char, u32, DynConstraint<char, u32>>::new(domains);
+        csp.add_constraint(Box::new(AllDifferent::new(vec!['a', 'b', 'c'])));
+        csp.set_propagation(Propagation::ArcConsistency);
+        assert!(csp.backtracking_search().is_none());
+    }
+
+    #[test]
+    fn enumerates_and_counts_solutions() {
+        for propagation in [
+            Propagation::None,
+            Propagation::ForwardChecking,
+            Propagation::ArcConsistency,
+        ] {
+            let mut csp = queens(8);
+            csp.set_propagation(propagation);
+            assert_eq!(csp.count_solutions(None), 92);
+            assert_eq!(csp.count_solutions(Some(10)), 10);
+        }
+        let csp = queens(6);
+        let solutions: Vec<HashMap<Rc<u8>, u8>> = csp.solutions().collect();
+        assert_eq!(solutions.len(), 4);
+        assert!(solutions
+            .iter()
+            .all(|solution| is_queens_solution(6, solution)));
+        assert!((1..4).all(|i| !solutions[..i].contains(&solutions[i])));
+        assert!(!csp.has_unique_solution());
+        assert!(!queens(3).has_unique_solution());
+
+        let domains = vec![('a', vec![1]), ('b', vec![1, 2]), ('c', vec![1, 2, 3])];
+        let mut csp = CSP::<char, u32, AllDifferent<char>>::new(domains);
+        csp.add_constraint(AllDifferent::new(vec!['a', 'b', 'c']));
+        assert!(csp.has_unique_solution());
+        assert_eq!(
+            CSP::<char, u32, AllDifferent<char>>::new([]).count_solutions(None),
+            1
+        );
+    }
+
+    #[test]
+    fn backjumping_skips_irrelevant_variables() {
+        // x0 and x9 must differ, x1 to x8 are unconstrained: after x9 fails for x0 = 1,
+        // chronological backtracking would try all 4^8 values of x1 to x8 first
+        let mut domains = vec![(0, vec![1, 2])];
+        domains.extend((1..9).map(|variable| (variable, vec![1, 2, 3, 4])));
+        domains.push((9, vec![1]));
+        let mut csp = CSP::<u8, u32, AllDifferent<u8>>::new(domains);
+        csp.add_constraint(AllDifferent::new(vec![0, 9]));
+        csp.set_variable_ordering(VariableOrdering::Declaration);
+        let mut backjumping = Backjumping::new(&csp);
+        let solution = backjumping.search().unwrap();
+        assert_eq!((solution[&0], solution[&9]), (2, 1));
+        assert_eq!(backjumping.backjumps(), 8);
+        assert_eq!(backjumping.nogoods(), [vec![(Rc::new(0), 1)]]);
+        // the learned nogood rules out x0 = 1 at once
+        backjumping.search().unwrap();
+        assert_eq!(backjumping.backjumps(), 8);
+
+        let mut csp = queens(8);
+        csp.set_variable_ordering(VariableOrdering::Declaration);
+        assert_eq!(csp.backjumping_search(), csp.backtracking_search());
+        let csp = queens(3);
+        let mut backjumping = Backjumping::new(&csp);
+        assert!(backjumping.search().is_none());
+        assert!(backjumping.nogoods().contains(&Vec::new()));
+
+        // x3 and x4 cannot differ whatever x0 to x2 are: the empty nogood is learned
+        // and a second search fails without trying anything
+        let mut domains: Vec<(u8, Vec<u32>)> =
+            (0..3).map(|variable| (variable, vec![1, 2])).collect();
+        domains.extend([(3, vec![1]), (4, vec![1])]);
+        let mut csp = CSP::<u8, u32, AllDifferent<u8>>::new(domains);
+        csp.add_constraint(AllDifferent::new(vec![3, 4]));
+        csp.set_variable_ordering(VariableOrdering::Declaration);
+        let mut backjumping = Backjumping::new(&csp);
+        assert!(backjumping.search().is_none());
+        assert_eq!(backjumping.backjumps(), 3);
+        assert!(backjumping.search().is_none());
+        assert_eq!(backjumping.backjumps(), 3);
+    }
+
+    #[test]
+    fn builder_reports_model_errors() {
+        let csp = CSPBuilder::new()
+            .variables([('a', vec![1, 2]), ('b', vec![1, 2])])
+            .constraint(AllDifferent::new(vec!['a', 'b']))
+            .propagation(Propagation::ArcConsistency)
+            .build()
+            .unwrap();
+        assert_eq!(csp.count_solutions(None), 2);
+
+        let builder = CSPBuilder::new()
+            .variable('a', vec![1, 2])
+            .variable('b', vec![])
+            .variable('a', vec![3])
+            .variable('c', vec![1])
+            .constraint(AllDifferent::new(vec!['a', 'x']))
+            .constraint(AllDifferent::new(vec![]));
+        let report = builder.validate();
+        assert_eq!(
+            report.errors,
+            [
+                CSPError::EmptyDomain('b'),
+                CSPError::DuplicateVariable('a'),
+                CSPError::UnknownVariable('x'),
+                CSPError::ConstraintWithoutVariables { index: 1 },
+            ]
+        );
+        assert_eq!(report.unconstrained, ['b', 'c']);
+        assert_eq!(report.search_space, 0);
+        assert!(!report.is_valid());
+        assert_eq!(
+            builder.build().err().map(|error| error.to_string()),
+            Some("variable 'b' has an empty domain".to_string())
+        );
+
+        let mut csp = CSP::<char, u32, AllDifferent<char>>::new([('a', vec![1])]);
+        assert_eq!(
+            csp.try_add_constraint(AllDifferent::new(vec!['a', 'z'])),
+            Err(CSPError::UnknownVariable('z'))
+        );
+    }
+
+    #[test]
+    fn mixes_constraint_types() {
+        let domains: HashMap<char, Vec<u32>> = ['a', 'b', 'c']
+            .iter()
+            .map(|&v| (v, vec![1, 2, 3, 4]))
+            .collect();
+        let mut csp = CSP::<char, u32, DynConstraint<char, u32>>::new(domains);
+        csp.add_constraint(Box::new(AllDifferent::new(vec!['a', 'b', 'c'])));
+        csp.add_constraint(Box::new(Sum {
+            variables: vec!['a', 'b'],
+            total: 7,
+        }));
+        csp.add_constraint(Box::new(Sum {
+            variables: vec!['b', 'c'],
+            total: 4,
+        }));
+        let solution = csp.backtracking_search().unwrap();
+        assert_eq!((solution[&'a'], solution[&'b'], solution[&'c']), (4, 3, 1));
+    }
+
+    #[test]
+    fn branch_and_bound_finds_optimum() {
+        let mut csp = CSP::new(['a', 'b', 'c'].map(|v| (v, vec![1, 2, 3, 4])));
+        csp.add_constraint(AllDifferent::new(vec!['a', 'b', 'c']));
+        let mut soft = SoftConstraints::new();
+        soft.add(
+            Sum {
+                variables: vec!['a', 'b'],
+                total: 7,
+            },
+            3,
+        );
+        soft.add(
+            Sum {
+                variables: vec!['b', 'c'],
+                total: 4,
+            },
+            2,
+        );
+        // a and c are different, so this one is always violated
+        soft.add(
+            Sum {
+                variables: vec!['a', 'c'],
+                total: 2,
+            },
+            4,
+        );
+        let costs: Vec<i64> = csp
+            .improving_solutions(&soft)
+            .map(|(_, cost)| cost)
+            .collect();
+        assert!(costs.windows(2).all(|pair| pair[0] > pair[1]));
+        let (solution, cost) = csp.minimise(&soft).unwrap();
+        assert_eq!(
+            (solution[&'a'], solution[&'b'], solution[&'c'], cost),
+            (4, 3, 1, 4)
+        );
+        assert_eq!(soft.violated(&solution).count(), 1);
+
+        // the largest three-digit number abc, with the digits so far plus 4s as the bound
+        let digits = |assignment: &HashMap<Rc<char>, u32>| {
+            ['a', 'b', 'c'].map(|v| i64::from(assignment.get(&v).copied().unwrap_or(4)))
+        };
+        let objective = Bounded {
+            cost: |assignment: &HashMap<Rc<char>, u32>| {
+                let [a, b, c] = digits(assignment);
+                -(100 * a + 10 * b + c)
+            },
+            lower_bound: |assignment: &HashMap<Rc<char>, u32>| {
+                let [a, b, c] = digits(assignment);
+                -(100 * a + 10 * b + c)
+            },
+        };
+        let brute_force = csp.solutions().map(|s| objective.cost(&s)).min();
+        assert_eq!(csp.minimise(&objective).map(|(_, cost)| cost), brute_force);
+        assert_eq!(brute_force, Some(-432));
+        assert_eq!(
+            csp.minimise(&|assignment: &HashMap<Rc<char>, u32>| i64::from(assignment[&'b']))
+                .map(|(solution, cost)| (solution[&'b'], cost)),
+            Some((1, 1))
+        );
+    }
+
+    #[test]
+    fn min_conflicts_solves_large_problems() {
+        let n: u8 = 60;
+        let csp = queen_pairs(n);
+        let result = csp.min_conflicts(&MinConflictsOptions::default()).unwrap();
+        assert!(result.is_solution());
+        assert!(is_queens_solution(n, &result.assignment));
+        assert_eq!(
+            result.steps,
+            csp.min_conflicts(&MinConflictsOptions::default())
+                .unwrap()
+                .steps
+        );
+
+        // a triangle cannot be coloured with two colours: the best leaves one edge violated
+        let mut csp = CSP::new(['a', 'b', 'c'].map(|v| (v, vec![1, 2])));
+        for edge in [['a', 'b'], ['b', 'c'], ['a', 'c']] {
+            csp.add_constraint(AllDifferent::new(edge.to_vec()));
+        }
+        let options = MinConflictsOptions {
+            max_steps: 50,
+            restarts: 2,
+            ..MinConflictsOptions::default()
+        };
+        let result = csp.min_conflicts(&options).unwrap();
+        assert!(!result.is_solution());
+        assert_eq!((result.violations, result.assignment.len()), (1, 3));
+        assert_eq!((result.steps, result.restarts), (150, 2));
+
+        // a solution reached on the last step of a restart is kept: with the same seed,
+        // the first start is the same, so another restart can only do better
+        let mut csp = CSP::new(['a', 'b', 'c'].map(|v| (v, vec![1, 2])));
+        for edge in [['a', 'b'], ['b', 'c']] {
+            csp.add_constraint(AllDifferent::new(edge.to_vec()));
+        }
+        for seed in 0..200 {
+            let options = MinConflictsOptions {
+                max_steps: 1,
+                restarts: 0,
+                random_walk: 0.0,
+                seed,
+                ..MinConflictsOptions::default()
+            };
+            let once = csp.min_conflicts(&options).unwrap();
+            let restarted = csp
+                .min_conflicts(&MinConflictsOptions {
+                    restarts: 1,
+                    ..options
+                })
+                .unwrap();
+            assert!(restarted.violations <= once.violations);
+        }
+    }
+
+    #[test]
+    fn min_conflicts_counts_violations_incrementally() {
+        // one constraint for all queens: a step takes time linear in the number of queens
+        let n: u16 = 10_000;
+        let mut csp = CSP::new((1..=n).map(|column| (column, (1..=n).collect())));
+        csp.add_constraint(AttackingQueens {
+            columns: (1..=n).collect(),
+        });
+        let result = csp.min_conflicts(&MinConflictsOptions::default()).unwrap();
+        assert!(result.is_solution());
+        assert_eq!(result.assignment.len(), n as usize);
+        assert!(csp.constraints[0].satisfied(&result.assignment));
+    }
+
+    #[test]
+    fn min_conflicts_rejects_what_it_cannot_search() {
+        // a variable without values is in conflict, but has no value to change to
+        let mut csp = CSP::new([('a', vec![5, 6]), ('b', vec![])]);
+        csp.add_constraint(Sum {
+            variables: vec!['a', 'b'],
+            total: 1,
+        });
+        assert_eq!(
+            csp.min_conflicts(&MinConflictsOptions::default())
+                .unwrap_err(),
+            CSPError::EmptyDomain('b')
+        );
+
+        // a random walk probability outside 0..=1 is taken as never or always
+        let csp = queen_pairs(8);
+        for random_walk in [-0.5, 1.5, f64::NAN] {
+            let options = MinConflictsOptions {
+                max_steps: 100,
+                random_walk,
+                ..MinConflictsOptions::default()
+            };
+            let result = csp.min_conflicts(&options).unwrap();
+            assert_eq!(result.assignment.len(), 8);
         }
     }
 }
@@ -0,0 +1,405 @@
+// csp/src/local_search.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Min-conflicts local search: start with a random complete assignment and repeatedly give
+// a variable in a violated constraint the value that violates the fewest constraints.
+// It cannot prove that there is no solution, but it handles problems far too large
+// for backtracking. A constraint that is not satisfied counts as one violation, so small
+// constraints guide the search best, e.g. one per pair of queens; as these grow
+// quadratically in number, a step for n queens then checks O(n^2) of them. A constraint
+// over many variables can instead count its violations itself with a ViolationCounter,
+// e.g. the pairs of attacking queens, updated for each change of a single variable: with
+// one such constraint for all queens, a step takes O(n) and 10,000 queens are solved.
+use crate::state::SearchState;
+use crate::{CSPError, Constraint, CSP};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+// Counts the violations of one constraint as its variables change one at a time,
+// see Constraint::violation_counter
+pub trait ViolationCounter<V, D> {
+    // Called for each variable of the constraint at every (re)start, with no old value,
+    // and then whenever one of them gets a new value
+    fn assign(&mut self, variable: &V, old: Option<&D>, new: &D);
+    // The violations of the current assignment, zero if it satisfies the constraint
+    fn violations(&self) -> usize;
+    // The violations the variable would be part of with the value instead of its own,
+    // the other variables keeping theirs
+    fn violations_with(&self, variable: &V, value: &D) -> usize;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinConflictsOptions {
+    // Steps after which the search starts again from a new random assignment
+    pub max_steps: usize,
+    // Number of new starts after the first one
+    pub restarts: usize,
+    // A variable does not get back a value it just had for this many steps,
+    // so that the search does not circle on a plateau
+    pub tabu_tenure: usize,
+    // Probability of a random value instead of the best one, to leave local minima;
+    // values outside 0..=1 are taken as the nearest of the two
+    pub random_walk: f64,
+    // Seed of the random number generator, so that results can be reproduced
+    pub seed: u64,
+}
+
+impl Default for MinConflictsOptions {
+    fn default() -> Self {
+        MinConflictsOptions {
+            max_steps: 100_000,
+            restarts: 10,
+            tabu_tenure: 10,
+            random_walk: 0.02,
+            seed: 42,
+        }
+    }
+}
+
+// The best assignment found: a solution if no constraint is violated
+#[derive(Debug, Clone)]
+pub struct MinConflictsResult<V, D> {
+    pub assignment: HashMap<Rc<V>, D>,
+    pub violations: usize,
+    pub steps: usize,
+    pub restarts: usize,
+}
+
+impl<V, D> MinConflictsResult<V, D> {
+    pub fn is_solution(&self) -> bool {
+        self.violations == 0
+    }
+}
+
+// A complete assignment with the violated constraints and the variables in them.
+// Constraints with a violation counter are left to it: the variables in conflict, and
+// violated, only cover the constraints without one.
+struct LocalSearch<'a, V: Eq + Hash, D: Clone, C: Constraint<V, D>> {
+    csp: &'a CSP<V, D, C>,
+    state: SearchState<V, D>,
+    // the violation counter of each constraint that has one, new for every start
+    counters: Vec<Option<Box<dyn ViolationCounter<V, D> + 'a>>>,
+    // the constraints with a counter
+    counted: Vec<usize>,
+    violated: Vec<bool>,
+    violations: usize,
+    // number of violated constraints of each variable
+    conflicts: Vec<usize>,
+    // the variables with conflicts, and the position of each variable in it
+    conflicted: Vec<usize>,
+    positions: Vec<Option<usize>>,
+}
+
+impl<'a, V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> LocalSearch<'a, V, D, C> {
+    fn new(csp: &'a CSP<V, D, C>) -> Self {
+        LocalSearch {
+            csp,
+            state: SearchState::new(csp),
+            counters: (0..csp.constraints.len()).map(|_| None).collect(),
+            counted: Vec::new(),
+            violated: vec![false; csp.constraints.len()],
+            violations: 0,
+            conflicts: vec![0; csp.variables.len()],
+            conflicted: Vec::new(),
+            positions: vec![None; csp.variables.len()],
+        }
+    }
+
+    // The value of an assigned variable
+    fn value(&self, variable: usize) -> &'a D {
+        &self.csp.values[variable][self.state.values[variable].unwrap()]
+    }
+
+    // Random values for all variables; no domain may be empty
+    fn randomise(&mut self, rng: &mut StdRng) {
+        let csp = self.csp;
+        for variable in 0..csp.variables.len() {
+            let value = rng.random_range(0..csp.values[variable].len());
+            self.state.assign(csp, variable, value);
+        }
+        self.violated.fill(false);
+        self.violations = 0;
+        self.conflicts.fill(0);
+        for variable in self.conflicted.drain(..) {
+            self.positions[variable] = None;
+        }
+        self.counted.clear();
+        for constraint in 0..csp.constraints.len() {
+            match csp.constraints[constraint].violation_counter() {
+                Some(mut counter) => {
+                    for &variable in &csp.scopes[constraint] {
+                        counter.assign(&csp.variables[variable], None, self.value(variable));
+                    }
+                    self.violations += counter.violations();
+                    self.counters[constraint] = Some(counter);
+                    self.counted.push(constraint);
+                }
+                None => self.update(constraint),
+            }
+        }
+    }
+
+    // Rechecks a constraint without counter after one of its variables has changed
+    fn update(&mut self, constraint: usize) {
+        let violated = !self.csp.constraints[constraint].satisfied(&self.state.assignment);
+        if violated == self.violated[constraint] {
+            return;
+        }
+        self.violated[constraint] = violated;
+        if violated {
+            self.violations += 1;
+        } else {
+            self.violations -= 1;
+        }
+        for &variable in &self.csp.scopes[constraint] {
+            if violated {
+                self.conflicts[variable] += 1;
+                if self.conflicts[variable] == 1 {
+                    self.positions[variable] = Some(self.conflicted.len());
+                    self.conflicted.push(variable);
+                }
+            } else {
+                self.conflicts[variable] -= 1;
+                if self.conflicts[variable] == 0 {
+                    let position = self.positions[variable].take().unwrap();
+                    self.conflicted.swap_remove(position);
+                    if let Some(&moved) = self.conflicted.get(position) {
+                        self.positions[moved] = Some(position);
+                    }
+                }
+            }
+        }
+    }
+
+    // Number of violations of the constraints of variable if it had the given value
+    fn violations_with(&mut self, variable: usize, value: usize) -> usize {
+        let csp = self.csp;
+        let current = self.state.values[variable].unwrap();
+        let mut violations = 0;
+        let mut assigned = false;
+        for &constraint in &csp.constraints_of[variable] {
+            match &self.counters[constraint] {
+                Some(counter) => {
+                    violations += counter
+                        .violations_with(&csp.variables[variable], &csp.values[variable][value]);
+                }
+                None => {
+                    // the constraint is checked with the value assigned
+                    if !assigned {
+                        self.state.assign(csp, variable, value);
+                        assigned = true;
+                    }
+                    if !csp.constraints[constraint].satisfied(&self.state.assignment) {
+                        violations += 1;
+                    }
+                }
+            }
+        }
+        if assigned {
+            self.state.assign(csp, variable, current);
+        }
+        violations
+    }
+
+    // Number of violations of the constraints of variable with its current value
+    fn current_violations(&self, variable: usize) -> usize {
+        let csp = self.csp;
+        self.conflicts[variable]
+            + csp.constraints_of[variable]
+                .iter()
+                .filter_map(|&constraint| self.counters[constraint].as_ref())
+                .map(|counter| {
+                    counter.violations_with(&csp.variables[variable], self.value(variable))
+                })
+                .sum::<usize>()
+    }
+
+    // A random variable in conflict: one of those in violated constraints without counter,
+    // or one in conflict in a constraint whose counter has violations. There must be a
+    // violation.
+    fn conflicted_variable(&self, rng: &mut StdRng) -> usize {
+        let violated_counters = || {
+            self.counted.iter().filter_map(|&constraint| {
+                let counter = self.counters[constraint].as_ref().unwrap();
+                (counter.violations() > 0).then_some((constraint, counter))
+            })
+        };
+        let choice = rng.random_range(0..self.conflicted.len() + violated_counters().count());
+        if choice < self.conflicted.len() {
+            return self.conflicted[choice];
+        }
+        let (constraint, counter) = violated_counters()
+            .nth(choice - self.conflicted.len())
+            .unwrap();
+        // the first variable in conflict from a random position on
+        let scope = &self.csp.scopes[constraint];
+        let start = rng.random_range(0..scope.len());
+        (start..scope.len())
+            .chain(0..start)
+            .map(|position| scope[position])
+            .find(|&variable| {
+                counter.violations_with(&self.csp.variables[variable], self.value(variable)) > 0
+            })
+            .unwrap_or(scope[start])
+    }
+
+    fn set(&mut self, variable: usize, value: usize) {
+        let csp = self.csp;
+        let old = self.value(variable);
+        self.state.assign(csp, variable, value);
+        for &constraint in &csp.constraints_of[variable] {
+            if let Some(counter) = self.counters[constraint].as_mut() {
+                self.violations -= counter.violations();
+                counter.assign(
+                    &csp.variables[variable],
+                    Some(old),
+                    &csp.values[variable][value],
+                );
+                self.violations += counter.violations();
+            } else {
+                self.update(constraint);
+            }
+        }
+    }
+}
+
+// The best assignment found. While the current assignment is as good as the best one,
+// it is only copied once a step makes it worse or a new start replaces it.
+struct Best<V, D> {
+    violations: usize,
+    assignment: Option<HashMap<Rc<V>, D>>,
+    current: bool,
+}
+
+impl<V: Eq + Hash, D: Clone> Best<V, D> {
+    fn new() -> Self {
+        Best {
+            violations: usize::MAX,
+            assignment: None,
+            current: false,
+        }
+    }
+
+    // To be called after every change of the current assignment
+    fn update(&mut self, violations: usize) {
+        if violations < self.violations {
+            self.violations = violations;
+            self.current = true;
+        }
+    }
+
+    // To be called before the current assignment is left for one with the given violations,
+    // None if it is replaced by a new start
+    fn keep(&mut self, assignment: &HashMap<Rc<V>, D>, violations: Option<usize>) {
+        if self.current && violations.is_none_or(|violations| violations > self.violations) {
+            self.assignment = Some(assignment.clone());
+            self.current = false;
+        }
+    }
+}
+
+pub(crate) fn min_conflicts<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>>(
+    csp: &CSP<V, D, C>,
+    options: &MinConflictsOptions,
+) -> Result<MinConflictsResult<V, D>, CSPError<V>> {
+    // without a value for every variable there is no complete assignment to start from
+    if let Some(variable) =
+        (0..csp.variables.len()).find(|&variable| csp.values[variable].is_empty())
+    {
+        return Err(CSPError::EmptyDomain(
+            csp.variables[variable].as_ref().clone(),
+        ));
+    }
+    let random_walk = if options.random_walk.is_nan() {
+        0.0
+    } else {
+        options.random_walk.clamp(0.0, 1.0)
+    };
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut search = LocalSearch::new(csp);
+    let mut best = Best::new();
+    let mut steps = 0;
+    let mut restarts = 0;
+    let mut candidates = Vec::new();
+    for restart in 0..=options.restarts {
+        restarts = restart;
+        search.randomise(&mut rng);
+        best.update(search.violations);
+        // tabu[variable]: the values variable must not get back, each with the step until
+        // which it must not; only few, as a variable changes once per step at most
+        let mut tabu: Vec<Vec<(usize, usize)>> = vec![Vec::new(); csp.variables.len()];
+        for _ in 0..options.max_steps {
+            if search.violations == 0 {
+                break;
+            }
+            steps += 1;
+            let variable = search.conflicted_variable(&mut rng);
+            let current = search.state.values[variable].unwrap();
+            // the violations of the other variables, which the step does not change
+            let others = search.violations - search.current_violations(variable);
+            let domain_size = csp.values[variable].len();
+            tabu[variable].retain(|&(_, until)| until > steps);
+            let (value, violations) = if rng.random_bool(random_walk) {
+                let value = rng.random_range(0..domain_size);
+                (value, search.violations_with(variable, value))
+            } else {
+                // among the values with the fewest violations, one at random
+                let mut fewest = usize::MAX;
+                candidates.clear();
+                for value in (0..domain_size).filter(|&value| value != current) {
+                    let violations = search.violations_with(variable, value);
+                    // a tabu value is only allowed if it leads to a new best assignment
+                    if others + violations >= best.violations
+                        && tabu[variable]
+                            .iter()
+                            .any(|&(tabu_value, _)| tabu_value == value)
+                    {
+                        continue;
+                    }
+                    if violations < fewest {
+                        fewest = violations;
+                        candidates.clear();
+                    }
+                    if violations == fewest {
+                        candidates.push(value);
+                    }
+                }
+                if candidates.is_empty() {
+                    continue;
+                }
+                (candidates[rng.random_range(0..candidates.len())], fewest)
+            };
+            best.keep(&search.state.assignment, Some(others + violations));
+            search.set(variable, value);
+            best.update(search.violations);
+            tabu[variable].push((current, steps + options.tabu_tenure));
+        }
+        // the last step may have improved on the best before the next restart starts over
+        best.keep(&search.state.assignment, None);
+        if best.violations == 0 {
+            break;
+        }
+    }
+    Ok(MinConflictsResult {
+        assignment: best.assignment.unwrap(),
+        violations: best.violations,
+        steps,
+        restarts,
+    })
+}
@@ -0,0 +1,169 @@
+// csp/src/optimisation.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Constraint optimisation by depth-first branch-and-bound: the constraints of the CSP are
+// hard, an Objective rates the solutions. Once a solution has been found, the search does
+// not go deeper where the lower bound of the objective shows that no better one can follow.
+use crate::search::Solutions;
+use crate::{Constraint, CSP};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+// A cost to be minimised (for a maximum, return the negated value)
+pub trait Objective<V, D> {
+    // The cost of a complete assignment
+    fn cost(&self, assignment: &HashMap<Rc<V>, D>) -> i64;
+    // A cost that no completion of the partial assignment can go below. The closer to the
+    // actual costs, the more of the search is cut off, but it must never be above them.
+    fn lower_bound(&self, _assignment: &HashMap<Rc<V>, D>) -> i64 {
+        i64::MIN
+    }
+}
+
+// Any function of the complete assignment, without a lower bound
+impl<V, D, F: Fn(&HashMap<Rc<V>, D>) -> i64> Objective<V, D> for F {
+    fn cost(&self, assignment: &HashMap<Rc<V>, D>) -> i64 {
+        self(assignment)
+    }
+}
+
+// An objective given by two functions, the cost and its lower bound
+pub struct Bounded<F, B> {
+    pub cost: F,
+    pub lower_bound: B,
+}
+
+impl<V, D, F, B> Objective<V, D> for Bounded<F, B>
+where
+    F: Fn(&HashMap<Rc<V>, D>) -> i64,
+    B: Fn(&HashMap<Rc<V>, D>) -> i64,
+{
+    fn cost(&self, assignment: &HashMap<Rc<V>, D>) -> i64 {
+        (self.cost)(assignment)
+    }
+    fn lower_bound(&self, assignment: &HashMap<Rc<V>, D>) -> i64 {
+        (self.lower_bound)(assignment)
+    }
+}
+
+// Constraints that may be violated at the cost of their weight
+pub struct SoftConstraints<S> {
+    constraints: Vec<(S, u32)>,
+}
+
+impl<S> SoftConstraints<S> {
+    pub fn new() -> Self {
+        SoftConstraints {
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, constraint: S, weight: u32) {
+        self.constraints.push((constraint, weight));
+    }
+
+    pub fn len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    // The constraints violated by the assignment with all their variables assigned,
+    // and their weights
+    pub fn violated<'a, V: Eq + Hash, D>(
+        &'a self,
+        assignment: &'a HashMap<Rc<V>, D>,
+    ) -> impl Iterator<Item = (&'a S, u32)> + 'a
+    where
+        S: Constraint<V, D>,
+    {
+        self.constraints
+            .iter()
+            .filter(|(constraint, _)| {
+                constraint
+                    .variables()
+                    .iter()
+                    .all(|variable| assignment.contains_key(variable))
+                    && !constraint.satisfied(assignment)
+            })
+            .map(|(constraint, weight)| (constraint, *weight))
+    }
+}
+
+impl<S> Default for SoftConstraints<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The weights of the violated soft constraints; those already violated by a partial
+// assignment stay violated, so their weights are a lower bound
+impl<V: Eq + Hash, D, S: Constraint<V, D>> Objective<V, D> for SoftConstraints<S> {
+    fn cost(&self, assignment: &HashMap<Rc<V>, D>) -> i64 {
+        self.lower_bound(assignment)
+    }
+    fn lower_bound(&self, assignment: &HashMap<Rc<V>, D>) -> i64 {
+        self.violated(assignment)
+            .map(|(_, weight)| i64::from(weight))
+            .sum()
+    }
+}
+
+// The solutions that are cheaper than all found before, with their costs;
+// the last one is optimal
+pub struct Improvements<'a, V: Eq + Hash, D: Clone, C: Constraint<V, D>, O: ?Sized> {
+    solutions: Solutions<'a, V, D, C>,
+    objective: &'a O,
+    best: Option<i64>,
+}
+
+impl<'a, V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>, O: Objective<V, D> + ?Sized>
+    Improvements<'a, V, D, C, O>
+{
+    pub(crate) fn new(csp: &'a CSP<V, D, C>, objective: &'a O) -> Self {
+        Improvements {
+            solutions: Solutions::new(csp),
+            objective,
+            best: None,
+        }
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>, O: Objective<V, D> + ?Sized> Iterator
+    for Improvements<'_, V, D, C, O>
+{
+    type Item = (HashMap<Rc<V>, D>, i64);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let objective = self.objective;
+            let solution = match self.best {
+                None => self.solutions.next()?,
+                Some(best) => self
+                    .solutions
+                    .next_where(|assignment| objective.lower_bound(assignment) >= best)?,
+            };
+            // with a weak lower bound, a solution may be no better than the best one
+            let cost = objective.cost(&solution);
+            if self.best.is_none_or(|best| cost < best) {
+                self.best = Some(cost);
+                return Some((solution, cost));
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+// csp/src/search.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Backtracking search on a SearchState: variable and value ordering, propagation
+// and the Solutions iterator
+use crate::state::{Domains, SearchState};
+use crate::{Constraint, Propagation, ValueOrdering, VariableOrdering, CSP};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+
+impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> CSP<V, D, C> {
+    // Whether the constraints of variable hold for the assignment
+    pub(crate) fn consistent(&self, variable: usize, assignment: &HashMap<Rc<V>, D>) -> bool {
+        self.constraints_of[variable]
+            .iter()
+            .all(|&constraint| self.constraints[constraint].satisfied(assignment))
+    }
+
+    // The values left in the domain of the unassigned variable that are consistent with
+    // the assignment. Each value is assigned in place of the one before, so the assignment
+    // only gains and loses the variable once.
+    fn consistent_values(&self, variable: usize, state: &mut SearchState<V, D>) -> usize {
+        let mut consistent = 0;
+        for value in 0..self.values[variable].len() {
+            if state.domains[variable].contains(value) {
+                state.assign(self, variable, value);
+                if self.consistent(variable, &state.assignment) {
+                    consistent += 1;
+                }
+            }
+        }
+        state.unassign(self, variable);
+        consistent
+    }
+
+    // Applied once before the search starts; false if the problem has no solution
+    pub(crate) fn propagate_initially(&self, state: &mut SearchState<V, D>) -> bool {
+        match self.propagation {
+            Propagation::None => true,
+            Propagation::ForwardChecking => self.prune(0..self.constraints.len(), state),
+            Propagation::ArcConsistency => {
+                self.prune(0..self.constraints.len(), state)
+                    && self.arc_consistency(0..self.variables.len(), state)
+            }
+        }
+    }
+
+    // Prunes the domains after variable has been assigned; false on a domain wipeout
+    pub(crate) fn propagate(&self, variable: usize, state: &mut SearchState<V, D>) -> bool {
+        let constraints = self.constraints_of[variable].iter().copied();
+        match self.propagation {
+            Propagation::None => true,
+            Propagation::ForwardChecking => {
+                self.forward_check(variable, state) && self.prune(constraints, state)
+            }
+            Propagation::ArcConsistency => {
+                self.forward_check(variable, state)
+                    && self.prune(constraints, state)
+                    && self.arc_consistency(self.neighbours()[variable].iter().copied(), state)
+            }
+        }
+    }
+
+    fn forward_check(&self, variable: usize, state: &mut SearchState<V, D>) -> bool {
+        for &neighbour in &self.neighbours()[variable] {
+            if state.is_assigned(neighbour) {
+                continue;
+            }
+            let mut wiped_out = false;
+            for value in 0..self.values[neighbour].len() {
+                if !state.domains[neighbour].contains(value) {
+                    continue;
+                }
+                state.assign(self, neighbour, value);
+                if !self.consistent(neighbour, &state.assignment) && !state.remove(neighbour, value)
+                {
+                    wiped_out = true;
+                    break;
+                }
+            }
+            state.unassign(self, neighbour);
+            if wiped_out {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Runs Constraint::prune for the given constraints, queueing the constraints of every
+    // variable with values removed, until nothing changes; false on a domain wipeout.
+    // The queue and the sets are the scratch buffers of the state, left empty.
+    fn prune(
+        &self,
+        constraints: impl Iterator<Item = usize>,
+        state: &mut SearchState<V, D>,
+    ) -> bool {
+        let mut queue = std::mem::take(&mut state.constraint_queue);
+        let mut queued = std::mem::take(&mut state.queued_constraints);
+        let mut changed = std::mem::take(&mut state.changed);
+        for constraint in constraints {
+            if queued.insert(constraint) {
+                queue.push_back(constraint);
+            }
+        }
+        let mut consistent = true;
+        while let Some(constraint) = queue.pop_front() {
+            queued.remove(constraint);
+            if !self.constraints[constraint].prune(&mut Domains::new(self, state, &mut changed)) {
+                consistent = false;
+                break;
+            }
+            for variable in changed.iter() {
+                for &other in &self.constraints_of[variable] {
+                    if other != constraint && queued.insert(other) {
+                        queue.push_back(other);
+                    }
+                }
+            }
+            changed.clear();
+        }
+        for constraint in queue.drain(..) {
+            queued.remove(constraint);
+        }
+        changed.clear();
+        state.constraint_queue = queue;
+        state.queued_constraints = queued;
+        state.changed = changed;
+        consistent
+    }
+
+    // Queues the arcs (x, y) from the unassigned neighbours x of y other than except,
+    // each with the position of x among the neighbours of y
+    fn push_arcs_into(
+        &self,
+        y: usize,
+        except: Option<usize>,
+        state: &mut SearchState<V, D>,
+        arcs: &mut VecDeque<(usize, usize, usize)>,
+    ) {
+        for (position, &x) in self.neighbours()[y].iter().enumerate() {
+            if !state.is_assigned(x) && Some(x) != except && state.queue_arc(y, position) {
+                arcs.push_back((x, y, position));
+            }
+        }
+    }
+
+    // AC-3 on the arcs into the unassigned variables ys: removes the values of x without
+    // a consistent value of y, for every arc (x, y), rechecking the arcs into x whenever
+    // its domain shrinks; false on a domain wipeout. The queue is the one of the state.
+    fn arc_consistency(
+        &self,
+        ys: impl Iterator<Item = usize>,
+        state: &mut SearchState<V, D>,
+    ) -> bool {
+        let mut arcs = std::mem::take(&mut state.arc_queue);
+        for y in ys {
+            if !state.is_assigned(y) {
+                self.push_arcs_into(y, None, state, &mut arcs);
+            }
+        }
+        let mut consistent = true;
+        while let Some((x, y, position)) = arcs.pop_front() {
+            state.dequeue_arc(y, position);
+            if !self.revise(x, y, state) {
+                continue;
+            }
+            if state.domains[x].is_empty() {
+                consistent = false;
+                break;
+            }
+            self.push_arcs_into(x, Some(y), state, &mut arcs);
+        }
+        for (_, y, position) in arcs.drain(..) {
+            state.dequeue_arc(y, position);
+        }
+        state.arc_queue = arcs;
+        consistent
+    }
+
+    // Removes the values of x without a consistent value of y; returns whether it removed any
+    fn revise(&self, x: usize, y: usize, state: &mut SearchState<V, D>) -> bool {
+        let mut revised = false;
+        for x_value in 0..self.values[x].len() {
+            if !state.domains[x].contains(x_value) {
+                continue;
+            }
+            state.assign(self, x, x_value);
+            let mut supported = false;
+            for y_value in 0..self.values[y].len() {
+                if state.domains[y].contains(y_value) {
+                    state.assign(self, y, y_value);
+                    if self.consistent(x, &state.assignment)
+                        && self.consistent(y, &state.assignment)
+                    {
+                        supported = true;
+                        break;
+                    }
+                }
+            }
+            state.unassign(self, y);
+            if !supported {
+                state.remove(x, x_value);
+                revised = true;
+            }
+        }
+        state.unassign(self, x);
+        revised
+    }
+
+    // Values of an unassigned variable still possible; propagation keeps the domains
+    // consistent with the assignment, without it they have to be checked
+    fn remaining_values(
+        &self,
+        variable: usize,
+        state: &mut SearchState<V, D>,
+        propagated: bool,
+    ) -> usize {
+        if propagated {
+            return state.domains[variable].len();
+        }
+        self.consistent_values(variable, state)
+    }
+
+    // Number of constraints shared with other unassigned variables
+    fn degree(&self, variable: usize, state: &SearchState<V, D>) -> usize {
+        self.constraints_of[variable]
+            .iter()
+            .filter(|&&constraint| {
+                self.scopes[constraint]
+                    .iter()
+                    .any(|&other| other != variable && !state.is_assigned(other))
+            })
+            .count()
+    }
+
+    pub(crate) fn select_unassigned_variable(
+        &self,
+        state: &mut SearchState<V, D>,
+        propagated: bool,
+    ) -> Option<usize> {
+        // only a strictly better variable replaces the best one, so ties go by declaration order
+        let mut best: Option<(usize, (usize, usize))> = None;
+        for variable in 0..self.variables.len() {
+            if state.is_assigned(variable) {
+                continue;
+            }
+            let key = match self.variable_ordering {
+                VariableOrdering::Declaration => return Some(variable),
+                VariableOrdering::MinimumRemainingValues => (
+                    self.remaining_values(variable, state, propagated),
+                    usize::MAX - self.degree(variable, state),
+                ),
+                VariableOrdering::Degree => (usize::MAX - self.degree(variable, state), 0),
+            };
+            if best.is_none_or(|(_, best_key)| key < best_key) {
+                best = Some((variable, key));
+            }
+        }
+        best.map(|(variable, _)| variable)
+    }
+
+    // Appends the values of variable left in its domain to values, in the order to try them
+    pub(crate) fn order_values(
+        &self,
+        variable: usize,
+        state: &mut SearchState<V, D>,
+        values: &mut Vec<usize>,
+    ) {
+        let start = values.len();
+        values.extend(state.domains[variable].iter());
+        if self.value_ordering == ValueOrdering::LeastConstrainingValue {
+            // the number of values each value rules out, computed once per value into the
+            // scratch buffer of the state; the sort is stable, so equally constraining values
+            // keep the domain order
+            let mut keyed = std::mem::take(&mut state.keyed_values);
+            for &value in &values[start..] {
+                state.assign(self, variable, value);
+                let mut ruled_out = 0;
+                for &neighbour in &self.neighbours()[variable] {
+                    if !state.is_assigned(neighbour) {
+                        ruled_out += state.domains[neighbour].len()
+                            - self.consistent_values(neighbour, state);
+                    }
+                }
+                keyed.push((ruled_out, value));
+            }
+            state.unassign(self, variable);
+            keyed.sort_by_key(|&(ruled_out, _)| ruled_out);
+            for (slot, (_, value)) in values[start..].iter_mut().zip(keyed.drain(..)) {
+                *slot = value;
+            }
+            state.keyed_values = keyed;
+        }
+    }
+}
+
+// A variable of the search in progress with the values still to try
+struct Frame {
+    variable: usize,
+    // the values to try are candidates[next..end]
+    next: usize,
+    end: usize,
+    start: usize,
+    // the trail position before the first value was tried
+    mark: usize,
+}
+
+// Backtracking search with an explicit stack instead of recursion,
+// so that it can stop at each solution and carry on from there
+pub struct Solutions<'a, V: Eq + Hash, D: Clone, C: Constraint<V, D>> {
+    csp: &'a CSP<V, D, C>,
+    state: SearchState<V, D>,
+    stack: Vec<Frame>,
+    // the values to try of all frames, one range after the other
+    candidates: Vec<usize>,
+    started: bool,
+}
+
+impl<'a, V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> Solutions<'a, V, D, C> {
+    pub(crate) fn new(csp: &'a CSP<V, D, C>) -> Self {
+        Solutions {
+            csp,
+            state: SearchState::new(csp),
+            stack: Vec::new(),
+            candidates: Vec::new(),
+            started: false,
+        }
+    }
+
+    // Pushes the next variable to assign; returns false if the assignment is complete
+    fn push_next_variable(&mut self) -> bool {
+        let csp = self.csp;
+        let propagated = csp.propagation != Propagation::None;
+        match csp.select_unassigned_variable(&mut self.state, propagated) {
+            Some(variable) => {
+                let start = self.candidates.len();
+                csp.order_values(variable, &mut self.state, &mut self.candidates);
+                self.stack.push(Frame {
+                    variable,
+                    next: start,
+                    end: self.candidates.len(),
+                    start,
+                    mark: self.state.mark(),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The next solution, not going deeper where prune returns true for the partial
+    // assignment, e.g. because it cannot lead to a better solution than one found before
+    pub(crate) fn next_where(
+        &mut self,
+        mut prune: impl FnMut(&HashMap<Rc<V>, D>) -> bool,
+    ) -> Option<HashMap<Rc<V>, D>> {
+        let csp = self.csp;
+        if !self.started {
+            self.started = true;
+            if !csp.propagate_initially(&mut self.state) {
+                return None;
+            }
+            if !self.push_next_variable() {
+                // no variables at all: the empty assignment is the only solution
+                return Some(HashMap::new());
+            }
+        }
+        while let Some(frame) = self.stack.last_mut() {
+            // undo the value tried last and its propagation
+            let variable = frame.variable;
+            self.state.undo(frame.mark);
+            self.state.unassign(csp, variable);
+            if frame.next == frame.end {
+                self.candidates.truncate(frame.start);
+                self.stack.pop();
+                continue;
+            }
+            let value = self.candidates[frame.next];
+            frame.next += 1;
+            self.state.assign(csp, variable, value);
+            // if we're still consistent, we go deeper (continue)
+            if csp.consistent(variable, &self.state.assignment)
+                && csp.propagate(variable, &mut self.state)
+                && !prune(&self.state.assignment)
+                && !self.push_next_variable()
+            {
+                // assignment is complete if every variable is assigned
+                return Some(self.state.assignment.clone());
+            }
+        }
+        None
+    }
+}
+
+impl<V: Eq + Hash + Clone, D: Clone, C: Constraint<V, D>> Iterator for Solutions<'_, V, D, C> {
+    type Item = HashMap<Rc<V>, D>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_where(|_| false)
+    }
+}
@@ -0,0 +1,341 @@
+// csp/src/state.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// The state of a search: variables and values are referred to by their indices in the CSP,
+// domains are bitsets over the value indices and every value removed from a domain is
+// recorded on a trail, so that backtracking undoes the removals instead of copying domains.
+// Constraint::satisfied still sees a HashMap with a copy of each value assigned, so a
+// search only works without allocating if cloning the values does not allocate, as for
+// numbers, chars or values behind an Rc (but not, e.g., a Vec of grid locations).
+use crate::{Constraint, Propagation, CSP};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    pub(crate) fn empty(capacity: usize) -> Self {
+        BitSet {
+            words: vec![0; capacity.div_ceil(64)],
+            len: 0,
+        }
+    }
+    pub(crate) fn full(capacity: usize) -> Self {
+        let mut words = vec![u64::MAX; capacity.div_ceil(64)];
+        if !capacity.is_multiple_of(64) {
+            words[capacity / 64] = (1 << (capacity % 64)) - 1;
+        }
+        BitSet {
+            words,
+            len: capacity,
+        }
+    }
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+    // Returns whether the index was not in the set before
+    pub(crate) fn insert(&mut self, index: usize) -> bool {
+        let added = !self.contains(index);
+        if added {
+            self.words[index / 64] |= 1 << (index % 64);
+            self.len += 1;
+        }
+        added
+    }
+    // Returns whether the index was in the set before
+    pub(crate) fn remove(&mut self, index: usize) -> bool {
+        let removed = self.contains(index);
+        if removed {
+            self.words[index / 64] &= !(1 << (index % 64));
+            self.len -= 1;
+        }
+        removed
+    }
+    pub(crate) fn clear(&mut self) {
+        if self.len > 0 {
+            self.words.fill(0);
+            self.len = 0;
+        }
+    }
+    // The indices in the set in ascending order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, &word)| {
+                let mut word = word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(word_index * 64 + bit)
+                })
+            })
+    }
+}
+
+pub(crate) struct SearchState<V, D> {
+    pub(crate) domains: Vec<BitSet>,
+    // (variable, value) of every value removed from a domain, latest last
+    trail: Vec<(usize, usize)>,
+    // the index of the value assigned to each variable
+    pub(crate) values: Vec<Option<usize>>,
+    // the same assignment in the form Constraint::satisfied expects
+    pub(crate) assignment: HashMap<Rc<V>, D>,
+    // the arcs (x, y) waiting in the AC-3 queue; the arc from the i-th neighbour x of y
+    // has the index arc_offsets[y] + i
+    arc_offsets: Vec<usize>,
+    queued_arcs: BitSet,
+    // scratch buffers of the search, kept to avoid allocating at every node
+    pub(crate) arc_queue: VecDeque<(usize, usize, usize)>,
+    pub(crate) constraint_queue: VecDeque<usize>,
+    pub(crate) queued_constraints: BitSet,
+    pub(crate) changed: BitSet,
+    pub(crate) keyed_values: Vec<(usize, usize)>,
+}
+
+impl<V: Eq + Hash + Clone, D: Clone> SearchState<V, D> {
+    pub(crate) fn new<C: Constraint<V, D>>(csp: &CSP<V, D, C>) -> Self {
+        // only AC-3 queues arcs; other searches need not work out the neighbours
+        let mut arc_offsets = Vec::new();
+        let mut arcs = 0;
+        if csp.propagation == Propagation::ArcConsistency {
+            for neighbours in csp.neighbours() {
+                arc_offsets.push(arcs);
+                arcs += neighbours.len();
+            }
+        }
+        SearchState {
+            domains: csp
+                .values
+                .iter()
+                .map(|values| BitSet::full(values.len()))
+                .collect(),
+            trail: Vec::new(),
+            values: vec![None; csp.variables.len()],
+            assignment: HashMap::with_capacity(csp.variables.len()),
+            arc_offsets,
+            queued_arcs: BitSet::empty(arcs),
+            arc_queue: VecDeque::new(),
+            constraint_queue: VecDeque::new(),
+            queued_constraints: BitSet::empty(csp.constraints.len()),
+            changed: BitSet::empty(csp.variables.len()),
+            keyed_values: Vec::new(),
+        }
+    }
+
+    // Marks the arc from the neighbour at position of y as queued; false if it was already
+    pub(crate) fn queue_arc(&mut self, y: usize, position: usize) -> bool {
+        self.queued_arcs.insert(self.arc_offsets[y] + position)
+    }
+
+    pub(crate) fn dequeue_arc(&mut self, y: usize, position: usize) {
+        self.queued_arcs.remove(self.arc_offsets[y] + position);
+    }
+
+    pub(crate) fn is_assigned(&self, variable: usize) -> bool {
+        self.values[variable].is_some()
+    }
+
+    pub(crate) fn assign<C: Constraint<V, D>>(
+        &mut self,
+        csp: &CSP<V, D, C>,
+        variable: usize,
+        value: usize,
+    ) {
+        // a variable assigned already gets the new value in place, keeping its key;
+        // the value itself is cloned, see above
+        let value_of = csp.values[variable][value].clone();
+        match self.values[variable].replace(value) {
+            Some(_) => *self.assignment.get_mut(&*csp.variables[variable]).unwrap() = value_of,
+            None => {
+                self.assignment
+                    .insert(Rc::clone(&csp.variables[variable]), value_of);
+            }
+        }
+    }
+
+    pub(crate) fn unassign<C: Constraint<V, D>>(&mut self, csp: &CSP<V, D, C>, variable: usize) {
+        if self.values[variable].take().is_some() {
+            self.assignment.remove(&csp.variables[variable]);
+        }
+    }
+
+    // Removes a value from a domain, to be restored by undo; returns false if that
+    // empties the domain
+    pub(crate) fn remove(&mut self, variable: usize, value: usize) -> bool {
+        if self.domains[variable].remove(value) {
+            self.trail.push((variable, value));
+        }
+        !self.domains[variable].is_empty()
+    }
+
+    // The position on the trail to return to with undo
+    pub(crate) fn mark(&self) -> usize {
+        self.trail.len()
+    }
+
+    pub(crate) fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let (variable, value) = self.trail.pop().unwrap();
+            self.domains[variable].insert(value);
+        }
+    }
+}
+
+// The domains as seen by Constraint::prune: the values still possible for each variable,
+// just the value assigned to it for an assigned variable
+pub struct Domains<'a, V, D> {
+    indices: &'a HashMap<Rc<V>, usize>,
+    values: &'a [Vec<D>],
+    state: &'a mut SearchState<V, D>,
+    // the variables with values removed
+    changed: &'a mut BitSet,
+}
+
+impl<'a, V: Eq + Hash + Clone, D: Clone> Domains<'a, V, D> {
+    pub(crate) fn new<C: Constraint<V, D>>(
+        csp: &'a CSP<V, D, C>,
+        state: &'a mut SearchState<V, D>,
+        changed: &'a mut BitSet,
+    ) -> Self {
+        Domains {
+            indices: &csp.indices,
+            values: &csp.values,
+            state,
+            changed,
+        }
+    }
+
+    // The variable must be one of the CSP
+    fn index(&self, variable: &V) -> usize {
+        self.indices[variable]
+    }
+
+    pub fn is_assigned(&self, variable: &V) -> bool {
+        self.state.is_assigned(self.index(variable))
+    }
+
+    pub fn values(&self, variable: &V) -> impl Iterator<Item = &D> + '_ {
+        let variable = self.index(variable);
+        let assigned = self.state.values[variable];
+        self.state.domains[variable]
+            .iter()
+            .filter(move |&value| assigned.is_none_or(|assigned| value == assigned))
+            .map(move |value| &self.values[variable][value])
+    }
+
+    pub fn len(&self, variable: &V) -> usize {
+        let variable = self.index(variable);
+        match self.state.values[variable] {
+            Some(_) => 1,
+            None => self.state.domains[variable].len(),
+        }
+    }
+
+    // Removes the values of variable for which keep returns false; returns false if no
+    // value is left (for an assigned variable: if its value is not kept)
+    pub fn retain(&mut self, variable: &V, mut keep: impl FnMut(&D) -> bool) -> bool {
+        let variable = self.index(variable);
+        if let Some(value) = self.state.values[variable] {
+            return keep(&self.values[variable][value]);
+        }
+        let removed: Vec<usize> = self.state.domains[variable]
+            .iter()
+            .filter(|&value| !keep(&self.values[variable][value]))
+            .collect();
+        if removed.is_empty() {
+            return true;
+        }
+        self.changed.insert(variable);
+        for value in removed {
+            self.state.remove(variable, value);
+        }
+        !self.state.domains[variable].is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitsets() {
+        let mut set = BitSet::full(130);
+        assert_eq!(set.len(), 130);
+        assert!(set.remove(64) && !set.remove(64));
+        assert!(set.remove(0));
+        assert_eq!(set.iter().take(3).collect::<Vec<usize>>(), [1, 2, 3]);
+        assert_eq!(set.iter().last(), Some(129));
+        assert_eq!(set.len(), 128);
+        let mut set = BitSet::empty(70);
+        assert!(set.insert(69) && set.insert(3) && !set.insert(3));
+        assert_eq!(set.iter().collect::<Vec<usize>>(), [3, 69]);
+        assert!(!set.is_empty());
+        set.clear();
+        assert!(set.is_empty() && set.iter().next().is_none());
+    }
+
+    struct Never {
+        variables: Vec<char>,
+    }
+
+    impl Constraint<char, u8> for Never {
+        fn satisfied(&self, _: &HashMap<Rc<char>, u8>) -> bool {
+            false
+        }
+        fn variables(&self) -> &Vec<char> {
+            &self.variables
+        }
+    }
+
+    #[test]
+    fn trail_undoes_removals() {
+        let csp = CSP::<char, u8, Never>::new([('a', vec![1, 2, 3]), ('b', vec![4])]);
+        let mut state = SearchState::new(&csp);
+        let start = state.mark();
+        assert!(state.remove(0, 1));
+        let middle = state.mark();
+        assert!(state.remove(0, 0) && state.remove(0, 0));
+        assert!(!state.remove(1, 0));
+        state.undo(middle);
+        assert_eq!(state.domains[0].iter().collect::<Vec<usize>>(), [0, 2]);
+        assert_eq!(state.domains[1].len(), 1);
+        state.undo(start);
+        assert_eq!(state.domains[0].len(), 3);
+        state.assign(&csp, 0, 0);
+        state.assign(&csp, 0, 2);
+        assert_eq!((state.assignment[&'a'], state.assignment.len()), (3, 1));
+        state.unassign(&csp, 0);
+        state.assign(&csp, 1, 0);
+        assert_eq!(state.assignment[&'b'], 4);
+        state.unassign(&csp, 1);
+        assert!(state.assignment.is_empty() && !state.is_assigned(1));
+    }
+}
@@ -0,0 +1,120 @@
+// csp/tests/allocations.rs
+// Extension of the Rust version of Classic Computer Science Problems in Python/Java Chapter 3
+// Copyright 2023 Markus Peter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Counts the allocations of a search, to check that the search allocates per node only
+// when cloning a value of a domain does. In an integration test of its own, so that the
+// counting allocator is not installed for the other tests.
+use csp::{Constraint, Propagation, ValueOrdering, CSP};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+struct CountingAllocator;
+
+thread_local! {
+    // per thread, as the tests run in parallel
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_of<R>(f: impl FnOnce() -> R) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    drop(f());
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+// A row of the queens, as a number or as a value that allocates when cloned
+trait Row: Clone {
+    fn row(&self) -> u8;
+}
+
+impl Row for u8 {
+    fn row(&self) -> u8 {
+        *self
+    }
+}
+
+impl Row for Vec<u8> {
+    fn row(&self) -> u8 {
+        self[0]
+    }
+}
+
+// The two queens do not attack each other
+struct QueenPair {
+    columns: Vec<u8>,
+}
+
+impl<D: Row> Constraint<u8, D> for QueenPair {
+    fn satisfied(&self, assignment: &HashMap<Rc<u8>, D>) -> bool {
+        let (column1, column2) = (self.columns[0], self.columns[1]);
+        match (assignment.get(&column1), assignment.get(&column2)) {
+            (Some(row1), Some(row2)) => {
+                let (row1, row2) = (row1.row(), row2.row());
+                row1 != row2 && column1.abs_diff(column2) != row1.abs_diff(row2)
+            }
+            _ => true,
+        }
+    }
+    fn variables(&self) -> &Vec<u8> {
+        &self.columns
+    }
+}
+
+fn queens<D: Row>(n: u8, row: impl Fn(u8) -> D) -> CSP<u8, D, QueenPair> {
+    let mut csp = CSP::new((1..=n).map(|column| (column, (1..=n).map(&row).collect())));
+    for column1 in 1..=n {
+        for column2 in column1 + 1..=n {
+            csp.add_constraint(QueenPair {
+                columns: vec![column1, column2],
+            });
+        }
+    }
+    csp.set_value_ordering(ValueOrdering::LeastConstrainingValue);
+    csp
+}
+
+#[test]
+fn search_allocates_per_node_only_for_values_that_allocate() {
+    // 92 solutions among thousands of nodes; each solution found is one copy of the
+    // assignment, the buffers of the search only grow a few times
+    for propagation in [Propagation::ForwardChecking, Propagation::ArcConsistency] {
+        let mut numbers = queens(8, |row| row);
+        numbers.set_propagation(propagation);
+        // the first search also works out the neighbours of the variables
+        numbers.has_unique_solution();
+        let allocations = allocations_of(|| numbers.count_solutions(None));
+        assert!(allocations < 92 + 50, "{allocations} allocations");
+    }
+
+    // a Vec as value is cloned for every value tried
+    let mut vectors = queens(8, |row| vec![row]);
+    vectors.set_propagation(Propagation::ForwardChecking);
+    assert!(allocations_of(|| vectors.count_solutions(None)) > 10_000);
+}
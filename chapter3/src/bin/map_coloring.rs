@@ -49,7 +49,8 @@ impl csp::Constraint<&str, &str> for MapColoringConstraint {
 
 fn main() {
     let colors = vec!["red", "green", "blue"];
-    let mut domains = HashMap::<&str, Vec<&str>>::new();
+    // a Vec instead of a HashMap keeps the order of the regions, so every run is the same
+    let mut domains = Vec::<(&str, Vec<&str>)>::new();
     for variable in &[
         "WesternAustralia",
         "NorthernTerritory",
@@ -59,7 +60,7 @@ fn main() {
         "Victoria",
         "Tasmania",
     ] {
-        domains.insert(variable, colors.clone());
+        domains.push((variable, colors.clone()));
     }
     let mut csp = csp::CSP::<&str, &str, MapColoringConstraint>::new(domains);
     csp.add_constraint(MapColoringConstraint::new(
@@ -93,4 +94,17 @@ fn main() {
         None => println!("No solution found!"),
         Some(solution) => println!("{:#?}", solution),
     }
+
+    // red paint is expensive: as few red regions as possible; the red regions so far
+    // are a lower bound, as they stay red
+    let red_regions = |assignment: &HashMap<Rc<&str>, &str>| {
+        assignment.values().filter(|&&color| color == "red").count() as i64
+    };
+    let objective = csp::Bounded {
+        cost: red_regions,
+        lower_bound: red_regions,
+    };
+    if let Some((solution, cost)) = csp.minimise(&objective) {
+        println!("{} red regions: {:#?}", cost, solution);
+    }
 }
@@ -56,9 +56,9 @@ impl csp::Constraint<u8, u8> for QueensConstraint {
 
 fn main() {
     let columns = Vec::from([1, 2, 3, 4, 5, 6, 7, 8]); // the "variables"
-    let mut rows = HashMap::<u8, Vec<u8>>::new(); // the "domains"
+    let mut rows = Vec::<(u8, Vec<u8>)>::new(); // the "domains", in the order of the columns
     for column in &columns {
-        rows.insert(*column, Vec::from([1, 2, 3, 4, 5, 6, 7, 8]));
+        rows.push((*column, Vec::from([1, 2, 3, 4, 5, 6, 7, 8])));
     }
 
     let mut csp = csp::CSP::<u8, u8, QueensConstraint>::new(rows);
@@ -68,4 +68,5 @@ fn main() {
         None => println!("No solution found!"),
         Some(solution) => println!("{:#?}", solution),
     }
+    println!("{} solutions in total", csp.count_solutions(None));
 }
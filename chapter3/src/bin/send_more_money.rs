@@ -34,14 +34,13 @@ impl csp::Constraint<char, u16> for SendMoreMoneyConstraint {
             return false;
         } else if assignment.len() == self.letters.len() {
             // if all variables have been assigned, check if it adds correctly
-            let s: u16 = *assignment.get(&'S').expect("S assignment missing");
-            let e: u16 = *assignment.get(&'E').expect("E assignment missing");
-            let n: u16 = *assignment.get(&'N').expect("N assignment missing");
-            let d: u16 = *assignment.get(&'D').expect("D assignment missing");
-            let m: u16 = *assignment.get(&'M').expect("M assignment missing");
-            let o: u16 = *assignment.get(&'O').expect("O assignment missing");
-            let r: u16 = *assignment.get(&'R').expect("R assignment missing");
-            let y: u16 = *assignment.get(&'Y').expect("Y assignment missing");
+            let digits: Option<Vec<u16>> = ['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y']
+                .iter()
+                .map(|letter| assignment.get(letter).copied())
+                .collect();
+            let Some(&[s, e, n, d, m, o, r, y]) = digits.as_deref() else {
+                return false; // a letter of the puzzle is not a variable of the CSP
+            };
             let send: u16 = 1000 * s + 100 * e + 10 * n + d;
             let more: u16 = 1000 * m + 100 * o + 10 * r + e;
             let money: u16 = 10000 * m + 1000 * o + 100 * n + 10 * e + y;
@@ -57,18 +56,61 @@ impl csp::Constraint<char, u16> for SendMoreMoneyConstraint {
 
 fn main() {
     let letters = vec!['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y'];
-    let mut possible_digits: HashMap<char, Vec<u16>> = HashMap::new();
+    let mut possible_digits: Vec<(char, Vec<u16>)> = Vec::new();
     for letter in &letters {
-        possible_digits.insert(*letter, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        if *letter == 'M' {
+            possible_digits.push((*letter, vec![1])); // so we don't get answers starting with a 0
+        } else {
+            possible_digits.push((*letter, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]));
+        }
     }
-    let m_digits = possible_digits.get_mut(&'M').unwrap();
-    *m_digits = vec![1]; // so we don't get answers starting with a 0
 
-    let mut csp = csp::CSP::<char, u16, SendMoreMoneyConstraint>::new(possible_digits);
-    csp.add_constraint(SendMoreMoneyConstraint::new(letters));
+    let builder = csp::CSPBuilder::new()
+        .variables(possible_digits.clone())
+        .constraint(SendMoreMoneyConstraint::new(letters.clone()));
+    println!("{}", builder.validate());
+    let csp = match builder.build() {
+        Ok(csp) => csp,
+        Err(error) => {
+            println!("Invalid model: {error}");
+            return;
+        }
+    };
     let solution = csp.backtracking_search();
     match solution {
         None => println!("No solution found!"),
         Some(solution) => println!("{:#?}", solution),
     }
+
+    // the same puzzle declared with global constraints: all letters are different digits
+    // and SEND + MORE - MONEY = 0, collected by place value
+    let builder = csp::CSPBuilder::<char, u16, csp::DynConstraint<char, u16>>::new()
+        .variables(possible_digits)
+        .constraint(Box::new(csp::AllDifferent::new(letters)))
+        .constraint(Box::new(csp::Linear::new(
+            vec![
+                (1000, 'S'),
+                (100 - 10 + 1, 'E'),
+                (10 - 100, 'N'),
+                (1, 'D'),
+                (1000 - 10000, 'M'),
+                (100 - 1000, 'O'),
+                (10, 'R'),
+                (-1, 'Y'),
+            ],
+            csp::Comparison::Equal,
+            0,
+        )));
+    let csp = match builder.build() {
+        Ok(csp) => csp,
+        Err(error) => {
+            println!("Invalid model: {error}");
+            return;
+        }
+    };
+    match csp.backtracking_search() {
+        None => println!("No solution found!"),
+        Some(solution) => println!("{:#?}", solution),
+    }
+    println!("unique solution: {}", csp.has_unique_solution());
 }
@@ -85,6 +85,7 @@ impl<V: Clone + PartialEq + std::fmt::Display> std::fmt::Display for UnweightedG
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn main() {
@@ -138,8 +139,12 @@ mod tests {
 
         println!("{}", city_graph.to_string());
 
-        let bfs_result =
-            generic_search::bfs("Boston", |v| *v == "Miami", |v| city_graph.neighbors_of(v));
+        let bfs_result = generic_search::bfs(
+            "Boston",
+            |v| *v == "Miami",
+            |v| city_graph.neighbors_of(v),
+            HashSet::new(),
+        );
 
         match bfs_result {
             None => println!("No solution found using breadth-first search"),
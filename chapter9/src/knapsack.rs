@@ -14,6 +14,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use memo::{Memo, Policy};
+
 #[derive(Clone, Debug)]
 pub struct Item {
     name: String,
@@ -52,6 +54,46 @@ pub fn knapsack(items: &[Item], max_capacity: usize) -> Vec<Item> {
     solution
 }
 
+// Best value of the items from index on within capacity, top-down instead of the table
+fn best_value(
+    items: &[Item],
+    index: usize,
+    capacity: usize,
+    memo: &mut Memo<(usize, usize), f64>,
+) -> f64 {
+    if index == items.len() {
+        return 0.0;
+    }
+    memo.get_or_insert_with((index, capacity), |memo| {
+        let without_item = best_value(items, index + 1, capacity, memo);
+        let item = &items[index];
+        if capacity >= item.weight {
+            without_item
+                .max(item.value + best_value(items, index + 1, capacity - item.weight, memo))
+        } else {
+            without_item
+        }
+    })
+}
+
+// The same solution as knapsack, but only the (index, capacity) combinations that are needed
+// are calculated; with a bounded policy, results that were dropped are recalculated
+pub fn knapsack_memoized(items: &[Item], max_capacity: usize, policy: Policy) -> Vec<Item> {
+    let mut memo = Memo::new(policy);
+    let mut capacity = max_capacity;
+    let mut solution: Vec<Item> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        // was this item used?
+        if best_value(items, index, capacity, &mut memo)
+            != best_value(items, index + 1, capacity, &mut memo)
+        {
+            solution.push(item.clone());
+            capacity -= item.weight;
+        }
+    }
+    solution
+}
+
 // Implement Display for Item to get rid of clippy's "field `name` is never read" warning
 impl std::fmt::Display for Item {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -129,4 +171,42 @@ mod tests {
         ];
         println!("{:#?}", knapsack(&items, 75));
     }
+
+    #[test]
+    fn memoized_knapsack_finds_the_same_value() {
+        let items: Vec<Item> = [
+            (50, 500.0),
+            (2, 300.0),
+            (35, 400.0),
+            (3, 1000.0),
+            (15, 50.0),
+        ]
+        .iter()
+        .chain(&[
+            (20, 800.0),
+            (1, 4000.0),
+            (100, 300.0),
+            (18, 30.0),
+            (10, 1000.0),
+        ])
+        .enumerate()
+        .map(|(index, &(weight, value))| Item {
+            name: format!("item {index}"),
+            weight,
+            value,
+        })
+        .collect();
+        let total = |solution: &[Item]| -> (usize, f64) {
+            (
+                solution.iter().map(|item| item.weight).sum(),
+                solution.iter().map(|item| item.value).sum(),
+            )
+        };
+        let expected = total(&knapsack(&items, 75)).1;
+        for policy in [Policy::Unbounded, Policy::Lru(16), Policy::SizeCapped(4)] {
+            let (weight, value) = total(&knapsack_memoized(&items, 75, policy));
+            assert!(weight <= 75);
+            assert_eq!(value, expected);
+        }
+    }
 }